@@ -0,0 +1,34 @@
+//! Cost of each perceptual hash on a 1080p frame.
+//!
+//! Run with `cargo bench --bench hashes`. Collision behaviour is covered by the
+//! dedup unit tests; this only measures time.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use image::{DynamicImage, Rgb, RgbImage};
+use recall_capture::dedup::HashAlgorithm;
+use std::hint::black_box;
+
+fn frame() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(1920, 1080, |x, y| {
+        let v = (x.wrapping_mul(31) ^ y.wrapping_mul(17)) as u8;
+        Rgb([v, v / 2, 255 - v])
+    }))
+}
+
+fn bench_hashes(c: &mut Criterion) {
+    let frame = frame();
+    let mut group = c.benchmark_group("hash_1080p");
+    for algorithm in [
+        HashAlgorithm::Average,
+        HashAlgorithm::Difference,
+        HashAlgorithm::Dct,
+    ] {
+        group.bench_function(format!("{algorithm:?}"), |b| {
+            b.iter(|| algorithm.hash(black_box(&frame)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_hashes);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// Frames are downscaled to at most this width before analysis. Wide enough that
+/// typical UI text strokes survive.
+const ANALYSIS_MAX_WIDTH: u32 = 1280;
+
+/// Luma step between horizontal neighbours that counts as an edge.
+const EDGE_DELTA: u8 = 48;
+
+/// Default minimum edge density for a frame to be worth running OCR on.
+pub const DEFAULT_OCR_TEXT_GATE_THRESHOLD: f64 = 0.02;
+
+/// Cheap estimate of how likely a frame contains text: the fraction of pixels with a
+/// sharp horizontal luma transition. Text is dense in such edges; video, photos and
+/// gradients mostly are not.
+pub fn text_likelihood(image: &DynamicImage) -> f64 {
+    let luma = if image.width() > ANALYSIS_MAX_WIDTH {
+        let height = (image.height() as u64 * ANALYSIS_MAX_WIDTH as u64 / image.width() as u64)
+            .max(1) as u32;
+        image
+            .resize_exact(ANALYSIS_MAX_WIDTH, height, FilterType::Triangle)
+            .to_luma8()
+    } else {
+        image.to_luma8()
+    };
+    if luma.width() < 2 || luma.height() == 0 {
+        return 0.0;
+    }
+
+    let edges: usize = luma
+        .as_raw()
+        .chunks_exact(luma.width() as usize)
+        .map(|row| {
+            row.windows(2)
+                .filter(|pair| pair[0].abs_diff(pair[1]) >= EDGE_DELTA)
+                .count()
+        })
+        .sum();
+    edges as f64 / ((luma.width() - 1) * luma.height()) as f64
+}
+
+/// Whether a frame should be queued for OCR under the given gate threshold.
+pub fn passes_text_gate(image: &DynamicImage, threshold: f64) -> bool {
+    text_likelihood(image) >= threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn text_like_image_passes_and_gradient_does_not() {
+        // Rows of 2px dark "glyph strokes" on a light background, like a page of text.
+        let text = DynamicImage::ImageLuma8(GrayImage::from_fn(640, 360, |x, y| {
+            let in_line = y % 20 < 12;
+            let in_stroke = x % 7 < 2;
+            Luma([if in_line && in_stroke { 20 } else { 240 }])
+        }));
+        let gradient = DynamicImage::ImageLuma8(GrayImage::from_fn(640, 360, |x, _| {
+            Luma([(x * 255 / 639) as u8])
+        }));
+
+        let threshold = DEFAULT_OCR_TEXT_GATE_THRESHOLD;
+        assert!(passes_text_gate(&text, threshold));
+        assert!(!passes_text_gate(&gradient, threshold));
+    }
+}
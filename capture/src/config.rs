@@ -0,0 +1,543 @@
+use crate::analysis::DEFAULT_OCR_TEXT_GATE_THRESHOLD;
+use crate::dedup::{
+    validate_dedup_threshold, validate_hamming_threshold, HashAlgorithm, DEFAULT_DEDUP_THRESHOLD,
+    DEFAULT_HAMMING_THRESHOLD,
+};
+use crate::dedup_policy::DEFAULT_POLICY;
+use crate::power::PowerPolicy;
+use crate::privacy::PrivacyBlocklist;
+use crate::supervisor::RestartPolicy;
+use crate::units::{legacy, ByteSize, HumanDuration};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// Slowest accepted capture rate: one frame a day. Anything slower is a typo, and an
+/// interval that long no longer fits in a `Duration`.
+pub const MIN_FPS: f64 = 1.0 / 86_400.0;
+
+/// Capture daemon configuration, loaded from TOML.
+///
+/// ```toml
+/// [global]
+/// fps = 1.0
+/// retention = "30d"
+///
+/// [[monitor]]
+/// name = "DELL U2720Q"
+/// fps = 0.2
+///
+/// [[monitor]]
+/// id = 65537
+/// enabled = false
+///
+/// [privacy]
+/// blocked_apps = ["1password", "^keepassxc$"]
+/// blocked_titles = ["^.* - my bank$"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub global: GlobalConfig,
+    #[serde(rename = "monitor")]
+    pub monitors: Vec<MonitorConfig>,
+    pub privacy: PrivacyConfig,
+}
+
+/// Windows that must never be recorded. Entries are case-insensitive substrings, or
+/// regexes when anchored with `^` or `$`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    pub blocked_apps: Vec<String>,
+    pub blocked_titles: Vec<String>,
+    /// Skip frames while the focused window can't be resolved (e.g. on Wayland), rather
+    /// than recording them unchecked.
+    pub fail_closed: bool,
+}
+
+impl PrivacyConfig {
+    pub fn blocklist(&self) -> Result<PrivacyBlocklist> {
+        PrivacyBlocklist::new(&self.blocked_apps, &self.blocked_titles)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GlobalConfig {
+    pub fps: f64,
+    /// How long frames are kept, e.g. "30d". A bare number means days.
+    /// Reserved, not yet used: nothing deletes frames until storage lands.
+    #[serde(alias = "retention_days", deserialize_with = "legacy::days")]
+    pub retention: HumanDuration,
+    /// JPEG quality (1-100) for stored frames. Reserved, not yet used: frames are not
+    /// encoded until storage lands.
+    pub jpeg_quality: u8,
+    /// Window for cross-frame dedup, e.g. "60s". A bare number means seconds.
+    /// Reserved, not yet used: only the in-memory dedup against the previous frame runs.
+    #[serde(alias = "dedup_window_secs", deserialize_with = "legacy::secs")]
+    pub dedup_window: HumanDuration,
+    /// Explicit capture channel capacity. Unset, [`Config::channel_capacities`] derives
+    /// it from the monitors, their fps and frame size. Reserved, not yet used: capture
+    /// doesn't feed bounded channels yet (see todo.md).
+    pub capture_channel_capacity: Option<usize>,
+    /// Explicit storage channel capacity, derived like `capture_channel_capacity` when
+    /// unset. Reserved, not yet used.
+    pub storage_channel_capacity: Option<usize>,
+    /// Seconds of capture the channels should absorb when storage falls behind.
+    pub channel_buffer_secs: f64,
+    /// Upper bound on memory held by frames queued in the channels, e.g. "512MiB".
+    /// A bare number means MiB.
+    #[serde(
+        alias = "channel_memory_ceiling_mb",
+        deserialize_with = "legacy::mebibytes"
+    )]
+    pub channel_memory_ceiling: ByteSize,
+    /// Frame diff below which a frame is dropped as a duplicate.
+    pub dedup_threshold: f64,
+    /// Maximum phash Hamming distance for two frames to count as duplicates in storage.
+    /// Logged at startup; not applied until storage dedup lands (see todo.md).
+    pub hamming_threshold: u32,
+    /// Perceptual hash: "average" (default), "difference" or "dct".
+    pub hash_algorithm: HashAlgorithm,
+    /// Count a frame that is mostly the previous one scrolled as only slightly changed.
+    pub scroll_aware_dedup: bool,
+    /// Posterize frames to this many bits per channel (1-8) before comparing them, so
+    /// dithering and compression noise don't count as change. Unset compares raw frames.
+    pub dedup_quantize_bits: Option<u8>,
+    /// Seconds without a changed frame before a monitor counts as inactive.
+    pub max_inactive_secs: u64,
+    /// Pause capture while on battery below this percentage.
+    pub pause_on_battery_below: Option<u8>,
+    /// Capture rate cap while on battery.
+    pub battery_fps: Option<f64>,
+    /// Skip capture after this long without keyboard/mouse input, e.g. "5m".
+    /// A bare number means seconds.
+    #[serde(alias = "idle_timeout_secs", deserialize_with = "legacy::opt_secs")]
+    pub idle_timeout: Option<HumanDuration>,
+    /// Widen the capture interval up to this multiple while the screen stays static for
+    /// `max_inactive_secs`. 1 disables idle backoff.
+    pub max_backoff_factor: u32,
+    /// Registered dedup policy deciding which frames reach storage.
+    pub dedup_policy: String,
+    /// Store only the final frame of a typing burst, once the screen has been settled
+    /// this long, e.g. "800ms". A bare number means milliseconds.
+    #[serde(alias = "text_debounce_ms", deserialize_with = "legacy::opt_millis")]
+    pub text_debounce: Option<HumanDuration>,
+    /// Edge density (0.0-1.0) at or above which a kept frame is flagged as likely text
+    /// and queued for OCR.
+    pub ocr_text_gate_threshold: f64,
+    /// Restart a monitor's capture task after a panic, with backoff, instead of leaving
+    /// that monitor unrecorded until the daemon restarts.
+    pub restart_on_panic: bool,
+}
+
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        Self {
+            fps: 1.0,
+            retention: HumanDuration(Duration::from_secs(30 * 24 * 60 * 60)),
+            jpeg_quality: 80,
+            dedup_window: HumanDuration(Duration::from_secs(60)),
+            capture_channel_capacity: None,
+            storage_channel_capacity: None,
+            channel_buffer_secs: 30.0,
+            channel_memory_ceiling: ByteSize(512 << 20),
+            dedup_threshold: DEFAULT_DEDUP_THRESHOLD,
+            hamming_threshold: DEFAULT_HAMMING_THRESHOLD,
+            hash_algorithm: HashAlgorithm::Average,
+            scroll_aware_dedup: false,
+            dedup_quantize_bits: None,
+            max_inactive_secs: 10,
+            pause_on_battery_below: None,
+            battery_fps: None,
+            idle_timeout: None,
+            max_backoff_factor: 1,
+            dedup_policy: DEFAULT_POLICY.to_string(),
+            text_debounce: None,
+            ocr_text_gate_threshold: DEFAULT_OCR_TEXT_GATE_THRESHOLD,
+            restart_on_panic: false,
+        }
+    }
+}
+
+/// Bounded channel sizes for the capture pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelCapacities {
+    pub capture: usize,
+    pub storage: usize,
+}
+
+/// Bytes held by one queued frame: the RGBA buffer shared through an `Arc`, plus the
+/// `Arc` header.
+pub fn frame_bytes(width: u32, height: u32) -> u64 {
+    width as u64 * height as u64 * 4 + std::mem::size_of::<usize>() as u64 * 2
+}
+
+/// Capacity that buffers `buffer_secs` of frames at `aggregate_fps`, but never more
+/// frames of `frame_bytes` than fit in `memory_ceiling_bytes`. Always at least 1.
+pub fn derive_channel_capacity(
+    aggregate_fps: f64,
+    buffer_secs: f64,
+    frame_bytes: u64,
+    memory_ceiling_bytes: u64,
+) -> usize {
+    // `as` saturates, and maps NaN to 0.
+    let wanted = (aggregate_fps * buffer_secs).ceil() as usize;
+    let affordable = (memory_ceiling_bytes / frame_bytes.max(1)) as usize;
+    wanted.min(affordable).max(1)
+}
+
+impl GlobalConfig {
+    /// Battery policy for capture tasks, or `None` when no battery setting is configured.
+    pub fn power_policy(&self) -> Option<PowerPolicy> {
+        let configured = self.pause_on_battery_below.is_some() || self.battery_fps.is_some();
+        configured.then_some(PowerPolicy {
+            pause_on_battery_below: self.pause_on_battery_below,
+            battery_fps: self.battery_fps,
+        })
+    }
+
+    /// How capture tasks are supervised.
+    pub fn restart_policy(&self) -> RestartPolicy {
+        RestartPolicy {
+            restart_on_panic: self.restart_on_panic,
+            ..Default::default()
+        }
+    }
+}
+
+/// Per-monitor overrides, matched by `id` or by `name` (case-insensitive).
+/// An entry that sets `id` matches only that id, whatever its `name`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub fps: Option<f64>,
+    pub dedup_threshold: Option<f64>,
+    pub enabled: Option<bool>,
+    pub max_inactive_secs: Option<u64>,
+}
+
+/// Effective settings for one monitor after merging global and per-monitor config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedMonitorConfig {
+    pub fps: f64,
+    pub dedup_threshold: f64,
+    pub enabled: bool,
+    pub max_inactive_secs: u64,
+}
+
+impl ResolvedMonitorConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps)
+    }
+}
+
+impl Config {
+    /// Parse a TOML config file. Missing keys fall back to defaults.
+    pub fn load(path: &Path) -> Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading config {}", path.display()))?;
+        let config: Config =
+            toml::from_str(&text).with_context(|| format!("parsing config {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject values that would break capture at runtime.
+    pub fn validate(&self) -> Result<()> {
+        let fps_values = std::iter::once(("global", self.global.fps))
+            .chain(self.global.battery_fps.map(|fps| ("battery", fps)))
+            .chain(
+                self.monitors
+                    .iter()
+                    .filter_map(|m| m.fps.map(|fps| ("monitor", fps))),
+            );
+        for (section, fps) in fps_values {
+            if !(fps.is_finite() && fps >= MIN_FPS) {
+                anyhow::bail!(
+                    "{} fps must be at least {} (one frame a day), got {}",
+                    section,
+                    MIN_FPS,
+                    fps
+                );
+            }
+        }
+        if let Some(percent) = self.global.pause_on_battery_below {
+            if percent > 100 {
+                anyhow::bail!("pause_on_battery_below must be 0-100, got {}", percent);
+            }
+        }
+        let thresholds = std::iter::once(self.global.dedup_threshold)
+            .chain(self.monitors.iter().filter_map(|m| m.dedup_threshold));
+        for threshold in thresholds {
+            validate_dedup_threshold(threshold)?;
+        }
+        validate_hamming_threshold(self.global.hamming_threshold)?;
+        if let Some(bits) = self.global.dedup_quantize_bits {
+            if !(1..=8).contains(&bits) {
+                anyhow::bail!("dedup_quantize_bits must be 1-8, got {}", bits);
+            }
+        }
+        let text_gate = self.global.ocr_text_gate_threshold;
+        if !(0.0..=1.0).contains(&text_gate) {
+            anyhow::bail!(
+                "ocr_text_gate_threshold must be between 0.0 and 1.0, got {}",
+                text_gate
+            );
+        }
+        let buffer_secs = self.global.channel_buffer_secs;
+        if !(buffer_secs.is_finite() && buffer_secs > 0.0) {
+            anyhow::bail!(
+                "channel_buffer_secs must be a positive number, got {}",
+                buffer_secs
+            );
+        }
+        if self.global.channel_memory_ceiling.0 == 0 {
+            anyhow::bail!("channel_memory_ceiling must be greater than zero");
+        }
+        for (name, capacity) in [
+            (
+                "capture_channel_capacity",
+                self.global.capture_channel_capacity,
+            ),
+            (
+                "storage_channel_capacity",
+                self.global.storage_channel_capacity,
+            ),
+        ] {
+            if capacity == Some(0) {
+                anyhow::bail!("{} must be at least 1", name);
+            }
+        }
+        self.privacy.blocklist()?;
+        Ok(())
+    }
+
+    /// Channel capacities for capture tasks described by `(fps, frame_bytes)`, one per
+    /// enabled monitor. Explicit capacities in `[global]` win.
+    ///
+    /// The capture channel buffers `channel_buffer_secs` at the aggregate fps. The
+    /// storage channel only sees frames that survived dedup and gets half of that.
+    /// Together they stay under `channel_memory_ceiling` of average-sized frames.
+    pub fn channel_capacities(&self, monitors: &[(f64, u64)]) -> ChannelCapacities {
+        let aggregate_fps: f64 = monitors.iter().map(|(fps, _)| fps).sum();
+        let avg_frame_bytes = match monitors.len() as u64 {
+            0 => 1,
+            n => monitors.iter().map(|(_, bytes)| bytes).sum::<u64>() / n,
+        };
+        // Capture holds two thirds of the budget, storage (at half the size) the rest.
+        let ceiling = self.global.channel_memory_ceiling.0 / 3 * 2;
+        let capture = derive_channel_capacity(
+            aggregate_fps,
+            self.global.channel_buffer_secs,
+            avg_frame_bytes,
+            ceiling,
+        );
+        let derived = ChannelCapacities {
+            capture,
+            storage: (capture / 2).max(1),
+        };
+        let capacities = ChannelCapacities {
+            capture: self
+                .global
+                .capture_channel_capacity
+                .unwrap_or(derived.capture),
+            storage: self
+                .global
+                .storage_channel_capacity
+                .unwrap_or(derived.storage),
+        };
+        tracing::info!(
+            "Channel capacities: capture {} (derived {}), storage {} (derived {}) for {} monitors at {:.2} fps, ~{} KiB/frame",
+            capacities.capture,
+            derived.capture,
+            capacities.storage,
+            derived.storage,
+            monitors.len(),
+            aggregate_fps,
+            avg_frame_bytes / 1024
+        );
+        capacities
+    }
+
+    /// Merge global settings with any `[[monitor]]` entries matching this monitor.
+    ///
+    /// Entries matched by name apply first and entries matched by id override them, so
+    /// an id-specific section wins over a shared name. Unknown monitors get the globals.
+    pub fn resolve_monitor(&self, id: u32, name: &str) -> ResolvedMonitorConfig {
+        let mut resolved = ResolvedMonitorConfig {
+            fps: self.global.fps,
+            dedup_threshold: self.global.dedup_threshold,
+            enabled: true,
+            max_inactive_secs: self.global.max_inactive_secs,
+        };
+
+        let by_name = self.monitors.iter().filter(|m| {
+            m.id.is_none()
+                && m.name
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        });
+        let by_id = self.monitors.iter().filter(|m| m.id == Some(id));
+        for entry in by_name.chain(by_id) {
+            resolved.fps = entry.fps.unwrap_or(resolved.fps);
+            resolved.dedup_threshold = entry.dedup_threshold.unwrap_or(resolved.dedup_threshold);
+            resolved.enabled = entry.enabled.unwrap_or(resolved.enabled);
+            resolved.max_inactive_secs = entry
+                .max_inactive_secs
+                .unwrap_or(resolved.max_inactive_secs);
+        }
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        [global]
+        fps = 2.0
+        dedup_threshold = 0.02
+
+        [[monitor]]
+        name = "dell u2720q"
+        fps = 0.5
+        max_inactive_secs = 30
+
+        [[monitor]]
+        id = 7
+        fps = 1.5
+        enabled = false
+    "#;
+
+    #[test]
+    fn per_monitor_overrides_merge_over_globals() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+
+        let by_name = config.resolve_monitor(3, "DELL U2720Q");
+        assert_eq!(by_name.fps, 0.5);
+        assert_eq!(by_name.max_inactive_secs, 30);
+        assert_eq!(by_name.dedup_threshold, 0.02);
+        assert!(by_name.enabled);
+
+        // The id entry overrides the name entry where both set a field.
+        let both = config.resolve_monitor(7, "Dell U2720Q");
+        assert_eq!(both.fps, 1.5);
+        assert_eq!(both.max_inactive_secs, 30);
+        assert!(!both.enabled);
+    }
+
+    #[test]
+    fn unknown_monitor_falls_through_to_globals() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+        let resolved = config.resolve_monitor(99, "Built-in Retina Display");
+
+        assert_eq!(
+            resolved,
+            ResolvedMonitorConfig {
+                fps: 2.0,
+                dedup_threshold: 0.02,
+                enabled: true,
+                max_inactive_secs: GlobalConfig::default().max_inactive_secs,
+            }
+        );
+        assert_eq!(
+            config.global.jpeg_quality,
+            GlobalConfig::default().jpeg_quality
+        );
+    }
+
+    #[test]
+    fn channel_capacities_scale_with_fps_and_respect_the_ceiling() {
+        let mut config = Config::default();
+        let hd = frame_bytes(1920, 1080);
+        let uhd = frame_bytes(3840, 2160);
+
+        // One slow monitor buffers 30 s of frames, not a fixed 64.
+        let slow = config.channel_capacities(&[(0.2, uhd)]);
+        assert_eq!(
+            slow,
+            ChannelCapacities {
+                capture: 6,
+                storage: 3
+            }
+        );
+
+        // Five monitors at 2 fps want 300 frames; 1080p frames fit under 512 MiB.
+        let busy = config.channel_capacities(&[(2.0, hd); 5]);
+        assert_eq!(busy.capture, 43);
+        assert!((busy.capture + busy.storage) as u64 * hd <= 512 << 20);
+
+        // Pathological settings stay bounded by the ceiling.
+        config.global.channel_buffer_secs = 3600.0;
+        let huge = config.channel_capacities(&[(60.0, uhd); 4]);
+        assert!((huge.capture + huge.storage) as u64 * uhd <= 512 << 20);
+        assert!(huge.storage >= 1);
+
+        // Explicit capacities override the derived ones.
+        config.global.capture_channel_capacity = Some(64);
+        config.global.storage_channel_capacity = Some(32);
+        assert_eq!(
+            config.channel_capacities(&[(0.2, uhd)]),
+            ChannelCapacities {
+                capture: 64,
+                storage: 32
+            }
+        );
+
+        assert_eq!(derive_channel_capacity(0.0, 30.0, hd, 1 << 30), 1);
+        assert_eq!(derive_channel_capacity(1.0, 30.0, u64::MAX, 1 << 30), 1);
+        assert_eq!(derive_channel_capacity(f64::NAN, 30.0, hd, 1 << 30), 1);
+    }
+
+    #[test]
+    fn legacy_integer_keys_still_load() {
+        let legacy: Config = toml::from_str(
+            "[global]\nretention_days = 7\nidle_timeout_secs = 300\nchannel_memory_ceiling_mb = 256\n",
+        )
+        .unwrap();
+        let typed: Config = toml::from_str(
+            "[global]\nretention = \"1w\"\nidle_timeout = \"5m\"\nchannel_memory_ceiling = \"256MiB\"\n",
+        )
+        .unwrap();
+        assert_eq!(legacy.global.retention, typed.global.retention);
+        assert_eq!(legacy.global.idle_timeout, typed.global.idle_timeout);
+        assert_eq!(
+            legacy.global.channel_memory_ceiling,
+            typed.global.channel_memory_ceiling
+        );
+        assert!(toml::from_str::<Config>("[global]\nretention = \"30\"\n").is_err());
+    }
+
+    #[test]
+    fn load_reads_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recall.toml");
+        std::fs::write(&path, SAMPLE).unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.monitors.len(), 2);
+        assert!(Config::load(&dir.path().join("missing.toml")).is_err());
+
+        for fps in ["0.0", "1e-300"] {
+            std::fs::write(&path, format!("[[monitor]]\nid = 1\nfps = {}\n", fps)).unwrap();
+            assert!(Config::load(&path).is_err(), "{fps}");
+        }
+        std::fs::write(&path, "[global]\nocr_text_gate_threshold = 1.5\n").unwrap();
+        assert!(Config::load(&path).is_err());
+        for bits in [0, 9] {
+            std::fs::write(&path, format!("[global]\ndedup_quantize_bits = {}\n", bits)).unwrap();
+            assert!(Config::load(&path).is_err(), "{bits}");
+        }
+        std::fs::write(&path, "[global]\ndedup_quantize_bits = 4\n").unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap().global.dedup_quantize_bits,
+            Some(4)
+        );
+    }
+}
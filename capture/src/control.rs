@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Runtime control for capture tasks, shared through a `watch` channel so every task
+/// sees the latest state, including tasks that start after it was set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlMessage {
+    #[default]
+    Resume,
+    Pause,
+}
+
+/// Channel for broadcasting pause/resume to capture tasks. Starts resumed.
+pub fn control_channel() -> (
+    watch::Sender<ControlMessage>,
+    watch::Receiver<ControlMessage>,
+) {
+    watch::channel(ControlMessage::Resume)
+}
+
+/// If capture is paused, wait until it is resumed and return how long that took.
+/// Returns `None` immediately when not paused.
+///
+/// Errors when the sender is dropped while paused, i.e. the daemon is shutting down.
+pub async fn wait_while_paused(
+    control: &mut watch::Receiver<ControlMessage>,
+) -> Result<Option<Duration>, watch::error::RecvError> {
+    if *control.borrow_and_update() != ControlMessage::Pause {
+        return Ok(None);
+    }
+    let paused_at = Instant::now();
+    control
+        .wait_for(|message| *message == ControlMessage::Resume)
+        .await?;
+    Ok(Some(paused_at.elapsed()))
+}
+
+/// Pause on SIGUSR1 and resume on SIGUSR2.
+///
+/// Unix only; on Windows pause/resume has to come from another control source.
+#[cfg(unix)]
+pub fn forward_pause_signals(
+    control: watch::Sender<ControlMessage>,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut pause = signal(SignalKind::user_defined1())?;
+    let mut resume = signal(SignalKind::user_defined2())?;
+    Ok(tokio::spawn(async move {
+        loop {
+            let message = tokio::select! {
+                Some(()) = pause.recv() => ControlMessage::Pause,
+                Some(()) = resume.recv() => ControlMessage::Resume,
+                else => break,
+            };
+            tracing::info!("Received {:?} signal", message);
+            if control.send(message).is_err() {
+                break;
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wait_while_paused_blocks_until_resume() {
+        let (tx, mut rx) = control_channel();
+        assert_eq!(wait_while_paused(&mut rx).await.unwrap(), None);
+
+        tx.send(ControlMessage::Pause).unwrap();
+        let resumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            tx.send(ControlMessage::Resume).unwrap();
+            tx
+        });
+        let paused_for = wait_while_paused(&mut rx).await.unwrap().unwrap();
+        assert!(paused_for >= Duration::from_millis(50), "{paused_for:?}");
+
+        let tx = resumer.await.unwrap();
+        tx.send(ControlMessage::Pause).unwrap();
+        drop(tx);
+        assert!(wait_while_paused(&mut rx).await.is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::path::Path;
+use tracing::debug;
+
+/// Load a stored frame, trusting the extension first and falling back to the file's
+/// magic bytes, so frames whose extension doesn't match their contents still load.
+pub fn load_image(path: &Path) -> Result<DynamicImage> {
+    let by_extension = match image::open(path) {
+        Ok(image) => return Ok(image),
+        Err(e) => e,
+    };
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let format = image::guess_format(&bytes)
+        .map_err(|_| anyhow::anyhow!("loading {}: {}", path.display(), by_extension))?;
+    debug!("{} sniffed as {:?}", path.display(), format);
+    image::load_from_memory_with_format(&bytes, format)
+        .with_context(|| format!("decoding {} as {:?}", path.display(), format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn load_image_sniffs_mislabeled_files() {
+        let frame = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 48, |x, y| {
+            Rgb([(x * 4) as u8, (y * 5) as u8, 90])
+        }));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frame.jpg");
+        frame
+            .save_with_format(&path, image::ImageFormat::Png)
+            .unwrap();
+
+        let loaded = load_image(&path).unwrap();
+        assert_eq!(loaded.to_rgb8(), frame.to_rgb8());
+
+        std::fs::write(&path, b"not an image").unwrap();
+        assert!(load_image(&path).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use serde::Deserialize;
+
+const HASH_SIDE: u32 = 8;
+
+/// Side of the luma image the DCT hash is computed from; the hash keeps the
+/// lowest 8x8 frequencies.
+const DCT_SIDE: u32 = 32;
+
+/// Hash returned for images with a zero-sized dimension.
+pub const EMPTY_PHASH: u64 = 0;
+
+/// 64-bit average hash: downscale to 8x8 luma and set one bit per pixel above the mean.
+///
+/// Frames narrower or shorter than 8px (degenerate monitors) are upscaled with
+/// nearest-neighbour instead, so every bit maps to a real source pixel rather than an
+/// interpolated blend. A uniform image hashes to 0, and a 0-sized image returns
+/// [`EMPTY_PHASH`] instead of panicking in the resize.
+pub fn phash64(image: &DynamicImage) -> u64 {
+    let Some(small) = hash_luma(image, HASH_SIDE, HASH_SIDE) else {
+        return EMPTY_PHASH;
+    };
+
+    let sum: u64 = small.pixels().map(|p| p.0[0] as u64).sum();
+    let mean = sum / (HASH_SIDE * HASH_SIDE) as u64;
+
+    small
+        .pixels()
+        .enumerate()
+        .filter(|(_, p)| p.0[0] as u64 > mean)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i))
+}
+
+/// 64-bit difference hash: downscale to 9x8 luma and set one bit per horizontally
+/// adjacent pair where brightness drops. Encodes gradients rather than absolute levels,
+/// so it is stable under brightness shifts and separates screens with the same
+/// light/dark layout but different content.
+pub fn dhash64(image: &DynamicImage) -> u64 {
+    let Some(small) = hash_luma(image, HASH_SIDE + 1, HASH_SIDE) else {
+        return EMPTY_PHASH;
+    };
+    let mut hash = 0u64;
+    for y in 0..HASH_SIDE {
+        for x in 0..HASH_SIDE {
+            if small.get_pixel(x, y).0[0] > small.get_pixel(x + 1, y).0[0] {
+                hash |= 1 << (y * HASH_SIDE + x);
+            }
+        }
+    }
+    hash
+}
+
+/// 64-bit DCT perceptual hash: downscale to 32x32 luma, take the lowest 8x8 DCT
+/// coefficients after the DC row and column and set one bit per coefficient above their
+/// median. Skipping DC keeps the hash stable under uniform brightness shifts. Robust to
+/// small shifts, scaling and gamma; the most expensive of the three.
+pub fn phash_dct64(image: &DynamicImage) -> u64 {
+    let Some(small) = hash_luma(image, DCT_SIDE, DCT_SIDE) else {
+        return EMPTY_PHASH;
+    };
+    let n = DCT_SIDE as usize;
+    let k = HASH_SIDE as usize;
+    let cosines: Vec<Vec<f64>> = (0..=k)
+        .map(|u| {
+            (0..n)
+                .map(|x| {
+                    ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * n) as f64).cos()
+                })
+                .collect()
+        })
+        .collect();
+
+    // Separable 2D DCT-II, computing only frequencies 1..=8 on each axis.
+    let pixels = small.as_raw();
+    let mut rows = vec![[0f64; HASH_SIDE as usize]; n];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = (0..n)
+                .map(|x| pixels[y * n + x] as f64 * cosines[u + 1][x])
+                .sum();
+        }
+    }
+    let mut coefficients = [0f64; (HASH_SIDE * HASH_SIDE) as usize];
+    for v in 0..k {
+        for u in 0..k {
+            coefficients[v * k + u] = (0..n).map(|y| rows[y][u] * cosines[v + 1][y]).sum();
+        }
+    }
+
+    let mut sorted = coefficients.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c > median)
+        .fold(0u64, |hash, (i, _)| hash | (1 << i))
+}
+
+/// Downscale to `width`x`height` luma for hashing, or `None` for a 0-sized image.
+/// Images smaller than the target use nearest-neighbour so bits map to real pixels.
+fn hash_luma(image: &DynamicImage, width: u32, height: u32) -> Option<GrayImage> {
+    if image.width() == 0 || image.height() == 0 {
+        return None;
+    }
+    let filter = if image.width() < width || image.height() < height {
+        FilterType::Nearest
+    } else {
+        FilterType::Triangle
+    };
+    Some(image.resize_exact(width, height, filter).to_luma8())
+}
+
+/// Which 64-bit perceptual hash the capture path computes. Hashes from different
+/// algorithms are not comparable with each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// [`phash64`], the original average hash.
+    #[default]
+    Average,
+    /// [`dhash64`].
+    Difference,
+    /// [`phash_dct64`].
+    Dct,
+}
+
+impl HashAlgorithm {
+    pub fn hash(&self, image: &DynamicImage) -> u64 {
+        match self {
+            HashAlgorithm::Average => phash64(image),
+            HashAlgorithm::Difference => dhash64(image),
+            HashAlgorithm::Dct => phash_dct64(image),
+        }
+    }
+
+    /// Name logged and stored next to a hash, so hashes from different algorithms are
+    /// never compared.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Average => "average",
+            HashAlgorithm::Difference => "difference",
+            HashAlgorithm::Dct => "dct",
+        }
+    }
+}
+
+/// Number of differing bits between two 64-bit hashes.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Default maximum Hamming distance for two hashes to count as the same screen.
+pub const DEFAULT_HAMMING_THRESHOLD: u32 = 10;
+
+/// Whether two hashes are within `max_distance` differing bits.
+pub fn is_similar(a: u64, b: u64, max_distance: u32) -> bool {
+    hamming_distance(a, b) <= max_distance
+}
+
+/// Reject Hamming thresholds that can't apply to a 64-bit hash.
+pub fn validate_hamming_threshold(threshold: u32) -> anyhow::Result<u32> {
+    if threshold <= 64 {
+        Ok(threshold)
+    } else {
+        anyhow::bail!("hamming threshold must be within 0..=64, got {}", threshold)
+    }
+}
+
+/// Frame diff below which the in-memory stage drops a frame.
+pub const DEFAULT_DEDUP_THRESHOLD: f64 = 0.01;
+
+/// Reject dedup thresholds outside `0.0..=1.0` (diffs are normalised to that range).
+pub fn validate_dedup_threshold(threshold: f64) -> anyhow::Result<f64> {
+    if (0.0..=1.0).contains(&threshold) {
+        Ok(threshold)
+    } else {
+        anyhow::bail!(
+            "dedup threshold must be within 0.0..=1.0, got {}",
+            threshold
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn phash64_handles_sub_hash_size_images() {
+        let single = DynamicImage::ImageLuma8(GrayImage::from_pixel(1, 1, Luma([200])));
+        assert_eq!(phash64(&single), 0);
+        assert_eq!(phash64(&single), phash64(&single.clone()));
+
+        let checker = DynamicImage::ImageLuma8(GrayImage::from_fn(3, 3, |x, y| {
+            Luma([if (x + y) % 2 == 0 { 255 } else { 0 }])
+        }));
+        let hash = phash64(&checker);
+        assert_ne!(hash, 0);
+        assert_eq!(hash, phash64(&checker.clone()));
+    }
+
+    #[test]
+    fn phash64_empty_image_returns_sentinel() {
+        let empty = DynamicImage::ImageLuma8(GrayImage::new(0, 5));
+        assert_eq!(phash64(&empty), EMPTY_PHASH);
+    }
+
+    #[test]
+    fn hamming_distance_counts_bits() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(u64::MAX, 0), 64);
+    }
+
+    /// Synthetic "screen": a dark sidebar and lines of text whose placement depends on `seed`.
+    fn screen(seed: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(256, 160, |x, y| {
+            if x < 64 {
+                return Luma([40]);
+            }
+            let line = y / 12;
+            let start = 72 + (line * 37 + seed * 53) % 60;
+            let len = 40 + (line * 91 + seed * 17) % 110;
+            let in_text = y % 12 < 7 && x >= start && x < start + len && (x + seed) % 20 >= 5;
+            Luma([if in_text { 30 } else { 220 }])
+        }))
+    }
+
+    const ALGORITHMS: [HashAlgorithm; 3] = [
+        HashAlgorithm::Average,
+        HashAlgorithm::Difference,
+        HashAlgorithm::Dct,
+    ];
+
+    #[test]
+    fn near_duplicates_stay_within_threshold_for_every_algorithm() {
+        let base = screen(1);
+        let brighter = DynamicImage::ImageLuma8(GrayImage::from_fn(256, 160, |x, y| {
+            Luma([base.to_luma8().get_pixel(x, y).0[0].saturating_add(20)])
+        }));
+        let mut cursor = base.to_luma8();
+        for y in 50..62 {
+            for x in 120..122 {
+                cursor.put_pixel(x, y, Luma([0]));
+            }
+        }
+        let cursor = DynamicImage::ImageLuma8(cursor);
+
+        for algorithm in ALGORITHMS {
+            for near in [&brighter, &cursor] {
+                let distance = hamming_distance(algorithm.hash(&base), algorithm.hash(near));
+                assert!(
+                    distance <= DEFAULT_HAMMING_THRESHOLD,
+                    "{algorithm:?}: {distance}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dct_hash_ignores_a_uniform_brightness_shift() {
+        let base = screen(3);
+        let brighter = DynamicImage::ImageLuma8(GrayImage::from_fn(256, 160, |x, y| {
+            Luma([base.to_luma8().get_pixel(x, y).0[0] + 30])
+        }));
+
+        assert_eq!(phash_dct64(&base), phash_dct64(&brighter));
+    }
+
+    #[test]
+    fn dct_hash_separates_screens_the_average_hash_collides_on() {
+        let collisions = |algorithm: HashAlgorithm| {
+            let base = algorithm.hash(&screen(1));
+            (2..12)
+                .filter(|&seed| {
+                    is_similar(
+                        base,
+                        algorithm.hash(&screen(seed)),
+                        DEFAULT_HAMMING_THRESHOLD,
+                    )
+                })
+                .count()
+        };
+
+        assert!(collisions(HashAlgorithm::Average) > 0);
+        assert_eq!(collisions(HashAlgorithm::Dct), 0);
+    }
+
+    #[test]
+    fn dhash_separates_gradients_with_the_same_light_dark_layout() {
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_fn(64, 64, |x, _| {
+            Luma([if x < 32 { 200 } else { 50 }])
+        }));
+        let graded = DynamicImage::ImageLuma8(GrayImage::from_fn(64, 64, |x, _| {
+            Luma([if x < 32 {
+                255 - x as u8 * 3
+            } else {
+                100 - (x as u8 - 32) * 3
+            }])
+        }));
+
+        assert_eq!(phash64(&flat), phash64(&graded));
+        assert!(hamming_distance(dhash64(&flat), dhash64(&graded)) > DEFAULT_HAMMING_THRESHOLD);
+        assert_eq!(
+            dhash64(&DynamicImage::ImageLuma8(GrayImage::new(0, 3))),
+            EMPTY_PHASH
+        );
+    }
+
+    #[test]
+    fn thresholds_change_similarity_classification() {
+        let a = 0u64;
+        let b = 0b1111_1111_1111u64; // 12 bits apart
+
+        assert!(!is_similar(a, b, DEFAULT_HAMMING_THRESHOLD));
+        assert!(is_similar(a, b, 12));
+        assert!(!is_similar(a, b, 11));
+        assert!(validate_hamming_threshold(64).is_ok());
+        assert!(validate_hamming_threshold(65).is_err());
+    }
+}
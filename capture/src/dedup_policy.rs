@@ -0,0 +1,390 @@
+use crate::dedup::{hamming_distance, HashAlgorithm};
+use crate::window_context::WindowContext;
+use anyhow::Result;
+use image::DynamicImage;
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Name of the built-in threshold policy.
+pub const DEFAULT_POLICY: &str = "default";
+
+/// Everything a [`DedupPolicy`] may look at for one captured frame.
+pub struct FrameContext<'a> {
+    pub image: &'a DynamicImage,
+    pub monitor_id: u32,
+    /// Normalised diff against the previous captured frame (0.0 = identical).
+    pub diff: f64,
+    /// Time since the last frame that was kept.
+    pub since_last_kept: Duration,
+    /// First frame after the user came back from idle.
+    pub resumed_from_idle: bool,
+    /// Focused window, when it was resolved before the decision (e.g. for privacy).
+    pub window: Option<&'a WindowContext>,
+    /// The last kept frame; `None` until a frame has been kept.
+    pub previous: Option<&'a KeptFrame>,
+    hash_algorithm: HashAlgorithm,
+    phash: OnceCell<u64>,
+}
+
+impl<'a> FrameContext<'a> {
+    pub fn new(
+        image: &'a DynamicImage,
+        monitor_id: u32,
+        diff: f64,
+        hash_algorithm: HashAlgorithm,
+    ) -> Self {
+        Self {
+            image,
+            monitor_id,
+            diff,
+            since_last_kept: Duration::ZERO,
+            resumed_from_idle: false,
+            window: None,
+            previous: None,
+            hash_algorithm,
+            phash: OnceCell::new(),
+        }
+    }
+
+    /// Algorithm behind [`FrameContext::phash`].
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Perceptual hash of the frame, computed on first use.
+    pub fn phash(&self) -> u64 {
+        *self
+            .phash
+            .get_or_init(|| self.hash_algorithm.hash(self.image))
+    }
+
+    /// Hamming distance to the last kept frame, computing both hashes if needed.
+    pub fn phash_distance(&self) -> Option<u32> {
+        self.previous
+            .map(|previous| hamming_distance(previous.phash(), self.phash()))
+    }
+
+    /// Keep this frame for later frames to compare against, along with its hash if it
+    /// was already computed. `image` must be the frame this context describes.
+    pub fn into_kept(self, image: Arc<DynamicImage>) -> KeptFrame {
+        KeptFrame {
+            image,
+            hash_algorithm: self.hash_algorithm,
+            phash: self.phash,
+        }
+    }
+}
+
+/// The last kept frame. Its hash is computed only once a later frame compares against it,
+/// so capture doesn't hash frames no policy or log event looks at.
+pub struct KeptFrame {
+    image: Arc<DynamicImage>,
+    hash_algorithm: HashAlgorithm,
+    phash: OnceCell<u64>,
+}
+
+impl KeptFrame {
+    /// Perceptual hash of the frame, computed on first use.
+    pub fn phash(&self) -> u64 {
+        *self
+            .phash
+            .get_or_init(|| self.hash_algorithm.hash(&self.image))
+    }
+}
+
+/// Tracing target for per-frame dedup decisions, so they can be filtered and aggregated.
+pub const DEDUP_DECISION_TARGET: &str = "recall_capture::dedup_decision";
+
+/// Outcome of a dedup stage for one frame.
+///
+/// Only the in-memory stage exists in this crate, so there is no `DedupDb` yet. Ticks
+/// skipped for power, idle or backoff never capture a frame, and nothing detects blank
+/// frames, so `Throttled` and `Blank` have no frame to describe; they belong with the
+/// storage task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupDecision {
+    Kept,
+    DedupMemory,
+}
+
+impl DedupDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DedupDecision::Kept => "kept",
+            DedupDecision::DedupMemory => "dedup_memory",
+        }
+    }
+}
+
+impl fmt::Display for DedupDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Emit the single structured event describing a frame's dedup decision, with the
+/// inputs the policy saw. Hashes the frames only while the target is enabled.
+pub fn log_dedup_decision(
+    ctx: &FrameContext<'_>,
+    policy: &str,
+    decision: DedupDecision,
+    reason: &str,
+) {
+    debug!(
+        target: DEDUP_DECISION_TARGET,
+        monitor_id = ctx.monitor_id,
+        phash = ctx.phash(),
+        hash_algorithm = ctx.hash_algorithm().as_str(),
+        diff = ctx.diff,
+        decision = decision.as_str(),
+        reason,
+        policy,
+        since_last_kept_ms = ctx.since_last_kept.as_millis() as u64,
+        resumed_from_idle = ctx.resumed_from_idle,
+        app = ctx.window.and_then(|w| w.app_name.as_deref()),
+        phash_distance = ctx.phash_distance(),
+        "dedup decision"
+    );
+}
+
+/// Decides whether a captured frame moves on to storage.
+pub trait DedupPolicy: Send {
+    fn name(&self) -> &'static str;
+
+    fn should_store(&mut self, ctx: &FrameContext<'_>) -> DedupDecision;
+
+    /// Why the last decision was made, for the dedup decision log.
+    fn reason(&self) -> &'static str {
+        self.name()
+    }
+}
+
+/// Inputs available to policy factories.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DedupPolicyParams {
+    /// Frame diff below which a frame counts as unchanged.
+    pub skip_threshold: f64,
+    /// Keep a frame at least this often, even if nothing changed.
+    pub max_skip: Duration,
+}
+
+/// In-memory dedup: drop frames that barely changed unless the heartbeat forces a keyframe.
+pub(crate) fn memory_dedup_decision(
+    diff: f64,
+    skip_threshold: f64,
+    force_capture: bool,
+) -> (DedupDecision, &'static str) {
+    if diff >= skip_threshold {
+        (DedupDecision::Kept, "changed")
+    } else if force_capture {
+        (DedupDecision::Kept, "heartbeat")
+    } else {
+        (DedupDecision::DedupMemory, "below_threshold")
+    }
+}
+
+/// The threshold-plus-heartbeat logic capture has always used.
+#[derive(Debug, Clone)]
+pub struct DefaultPolicy {
+    params: DedupPolicyParams,
+    reason: &'static str,
+}
+
+impl DefaultPolicy {
+    pub fn new(params: DedupPolicyParams) -> Self {
+        Self { params, reason: "" }
+    }
+}
+
+impl DedupPolicy for DefaultPolicy {
+    fn name(&self) -> &'static str {
+        DEFAULT_POLICY
+    }
+
+    fn should_store(&mut self, ctx: &FrameContext<'_>) -> DedupDecision {
+        let (decision, reason) = if ctx.resumed_from_idle {
+            (DedupDecision::Kept, "idle_resume")
+        } else {
+            memory_dedup_decision(
+                ctx.diff,
+                self.params.skip_threshold,
+                ctx.since_last_kept >= self.params.max_skip,
+            )
+        };
+        self.reason = reason;
+        decision
+    }
+
+    fn reason(&self) -> &'static str {
+        self.reason
+    }
+}
+
+/// Wraps a policy and records every decision with its inputs as the dedup decision
+/// event (see [`log_dedup_decision`]). Capture always wraps its policy in this.
+pub struct LoggingPolicy<P> {
+    inner: P,
+}
+
+impl<P: DedupPolicy> LoggingPolicy<P> {
+    pub fn new(inner: P) -> Self {
+        Self { inner }
+    }
+}
+
+impl<P: DedupPolicy> DedupPolicy for LoggingPolicy<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn should_store(&mut self, ctx: &FrameContext<'_>) -> DedupDecision {
+        let decision = self.inner.should_store(ctx);
+        log_dedup_decision(ctx, self.inner.name(), decision, self.inner.reason());
+        decision
+    }
+
+    fn reason(&self) -> &'static str {
+        self.inner.reason()
+    }
+}
+
+impl DedupPolicy for Box<dyn DedupPolicy> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn should_store(&mut self, ctx: &FrameContext<'_>) -> DedupDecision {
+        (**self).should_store(ctx)
+    }
+
+    fn reason(&self) -> &'static str {
+        (**self).reason()
+    }
+}
+
+pub type DedupPolicyFactory = fn(&DedupPolicyParams) -> Box<dyn DedupPolicy>;
+
+/// Policies selectable by name from config. Starts with [`DefaultPolicy`]; other crates
+/// register their own (typically behind a feature) before spawning capture.
+#[derive(Debug, Clone)]
+pub struct DedupPolicyRegistry {
+    factories: BTreeMap<String, DedupPolicyFactory>,
+}
+
+impl Default for DedupPolicyRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            factories: BTreeMap::new(),
+        };
+        registry.register(DEFAULT_POLICY, |params| {
+            Box::new(DefaultPolicy::new(params.clone()))
+        });
+        registry
+    }
+}
+
+impl DedupPolicyRegistry {
+    /// Add or replace the policy called `name`.
+    pub fn register(&mut self, name: impl Into<String>, factory: DedupPolicyFactory) {
+        self.factories.insert(name.into(), factory);
+    }
+
+    /// Build the policy called `name`, wrapped in [`LoggingPolicy`].
+    pub fn build(&self, name: &str, params: &DedupPolicyParams) -> Result<Box<dyn DedupPolicy>> {
+        let factory = self.factories.get(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "unknown dedup policy '{}' (available: {})",
+                name,
+                self.factories
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+        Ok(Box::new(LoggingPolicy::new(factory(params))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    struct KeepEverything;
+
+    impl DedupPolicy for KeepEverything {
+        fn name(&self) -> &'static str {
+            "keep_everything"
+        }
+
+        fn should_store(&mut self, _ctx: &FrameContext<'_>) -> DedupDecision {
+            DedupDecision::Kept
+        }
+    }
+
+    #[test]
+    fn kept_frames_are_hashed_only_when_compared() {
+        let first = Arc::new(DynamicImage::ImageLuma8(GrayImage::from_fn(
+            64,
+            64,
+            |x, _| Luma([x as u8 * 4]),
+        )));
+        let second = DynamicImage::ImageLuma8(GrayImage::from_pixel(64, 64, Luma([40])));
+
+        let kept =
+            FrameContext::new(&first, 0, 1.0, HashAlgorithm::Average).into_kept(Arc::clone(&first));
+        assert_eq!(kept.phash.get(), None);
+
+        let mut ctx = FrameContext::new(&second, 0, 1.0, HashAlgorithm::Average);
+        ctx.previous = Some(&kept);
+        assert_eq!(
+            ctx.phash_distance(),
+            Some(hamming_distance(
+                HashAlgorithm::Average.hash(&first),
+                HashAlgorithm::Average.hash(&second)
+            ))
+        );
+        assert_eq!(kept.phash.get(), Some(&HashAlgorithm::Average.hash(&first)));
+
+        // A hash the policy already computed carries over to the kept frame.
+        let kept = ctx.into_kept(Arc::new(second.clone()));
+        assert_eq!(
+            kept.phash.get(),
+            Some(&HashAlgorithm::Average.hash(&second))
+        );
+    }
+
+    #[test]
+    fn registry_builds_default_and_registered_policies() {
+        let params = DedupPolicyParams {
+            skip_threshold: 0.01,
+            max_skip: Duration::from_secs(10),
+        };
+        let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(16, 16, Luma([40])));
+        let mut ctx = FrameContext::new(&image, 0, 0.001, HashAlgorithm::Average);
+
+        let mut registry = DedupPolicyRegistry::default();
+        let mut default = registry.build(DEFAULT_POLICY, &params).unwrap();
+        assert_eq!(default.should_store(&ctx), DedupDecision::DedupMemory);
+        assert_eq!(default.reason(), "below_threshold");
+        ctx.since_last_kept = Duration::from_secs(11);
+        assert_eq!(default.should_store(&ctx), DedupDecision::Kept);
+        assert_eq!(default.reason(), "heartbeat");
+        ctx.since_last_kept = Duration::ZERO;
+        ctx.resumed_from_idle = true;
+        assert_eq!(default.should_store(&ctx), DedupDecision::Kept);
+        assert_eq!(default.reason(), "idle_resume");
+
+        assert!(registry.build("keep_everything", &params).is_err());
+        registry.register("keep_everything", |_| Box::new(KeepEverything));
+        let mut custom = registry.build("keep_everything", &params).unwrap();
+        ctx.resumed_from_idle = false;
+        assert_eq!(custom.should_store(&ctx), DedupDecision::Kept);
+        assert_eq!(custom.name(), "keep_everything");
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// File under the state directory holding the generated fallback id.
+const DEPLOYMENT_ID_FILE: &str = "deployment_id";
+
+/// Resolve the `deployment_id` stamped on every stored row.
+///
+/// Precedence: explicit override (CLI/env/config), then the hostname, then a
+/// generated id persisted in `state_dir` so it stays stable across restarts in
+/// environments where the hostname is unavailable (e.g. minimal containers).
+pub fn resolve_deployment_id(override_id: Option<&str>, state_dir: &Path) -> Result<String> {
+    let hostname = match hostname::get() {
+        Ok(name) => Some(name.to_string_lossy().into_owned()),
+        Err(e) => {
+            warn!("Could not read hostname for deployment_id: {}", e);
+            None
+        }
+    };
+    resolve_with_hostname(override_id, hostname, state_dir)
+}
+
+fn resolve_with_hostname(
+    override_id: Option<&str>,
+    hostname: Option<String>,
+    state_dir: &Path,
+) -> Result<String> {
+    if let Some(id) = override_id.map(str::trim).filter(|id| !id.is_empty()) {
+        return Ok(id.to_string());
+    }
+    if let Some(name) = hostname
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+    {
+        return Ok(name);
+    }
+    persisted_deployment_id(state_dir)
+}
+
+fn persisted_deployment_id(state_dir: &Path) -> Result<String> {
+    let path = state_dir.join(DEPLOYMENT_ID_FILE);
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if !existing.is_empty() {
+            return Ok(existing.to_string());
+        }
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    std::fs::create_dir_all(state_dir)
+        .with_context(|| format!("creating state dir {}", state_dir.display()))?;
+    std::fs::write(&path, &id).with_context(|| format!("writing {}", path.display()))?;
+    info!("Generated deployment_id {} ({})", id, path.display());
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_takes_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let id = resolve_with_hostname(Some(" laptop-1 "), Some("host".into()), dir.path());
+        assert_eq!(id.unwrap(), "laptop-1");
+
+        let id = resolve_with_hostname(Some(""), Some("host".into()), dir.path());
+        assert_eq!(id.unwrap(), "host");
+    }
+
+    #[test]
+    fn persisted_fallback_is_stable() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = resolve_with_hostname(None, None, dir.path()).unwrap();
+        let second = resolve_with_hostname(None, Some(" ".into()), dir.path()).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(DEPLOYMENT_ID_FILE)).unwrap(),
+            first
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use image::imageops::FilterType;
-use image::DynamicImage;
+use image::{DynamicImage, GenericImageView, GrayImage};
 use image_compare::Metric;
 use std::hash::{DefaultHasher, Hash, Hasher};
-use tracing::debug;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct FrameComparisonConfig {
@@ -10,6 +10,13 @@ pub struct FrameComparisonConfig {
     pub downscale_comparison: bool,
     pub downscale_factor: u32,
     pub single_metric: bool,
+    /// Keep only the top N bits (1-8) of each channel in the compared image, so
+    /// dithering and anti-aliasing noise doesn't register as change. The stored
+    /// frame is never altered.
+    pub quantize_bits: Option<u8>,
+    /// Discount the diff by how much of the frame is explained by a pure vertical or
+    /// horizontal shift of the previous frame, so scrolling a long page isn't "new".
+    pub scroll_aware: bool,
 }
 
 impl Default for FrameComparisonConfig {
@@ -19,6 +26,8 @@ impl Default for FrameComparisonConfig {
             downscale_comparison: true,
             downscale_factor: 6,
             single_metric: true,
+            quantize_bits: None,
+            scroll_aware: false,
         }
     }
 }
@@ -27,7 +36,10 @@ pub struct FrameComparer {
     config: FrameComparisonConfig,
     previous_hash: Option<u64>,
     previous_image_downscaled: Option<DynamicImage>,
-    previous_image_full: Option<DynamicImage>,
+    /// The previous frame itself (or its posterized copy), shared rather than copied.
+    previous_image_full: Option<Arc<DynamicImage>>,
+    previous_lines: Option<LineSignatures>,
+    previous_dimensions: Option<(u32, u32)>,
     comparison_count: u64,
     hash_hits: u64,
 }
@@ -39,19 +51,18 @@ impl FrameComparer {
             previous_hash: None,
             previous_image_downscaled: None,
             previous_image_full: None,
+            previous_lines: None,
+            previous_dimensions: None,
             comparison_count: 0,
             hash_hits: 0,
         }
     }
 
     fn downscale_dims(&self, width: u32, height: u32) -> (u32, u32) {
-        if self.config.downscale_factor > 0 {
-            (
-                (width / self.config.downscale_factor).max(1),
-                (height / self.config.downscale_factor).max(1),
-            )
-        } else {
-            (width, height)
+        let factor = self.config.downscale_factor;
+        match (width.checked_div(factor), height.checked_div(factor)) {
+            (Some(w), Some(h)) => (w.max(1), h.max(1)),
+            _ => (width, height),
         }
     }
 
@@ -60,23 +71,74 @@ impl FrameComparer {
         image.resize_exact(w, h, FilterType::Nearest)
     }
 
+    fn quantize(&self, image: DynamicImage) -> DynamicImage {
+        match self.config.quantize_bits {
+            Some(bits) => posterize(&image, bits),
+            None => image,
+        }
+    }
+
     fn hash_image(&self, downscaled: &DynamicImage) -> u64 {
         let mut hasher = DefaultHasher::new();
         downscaled.as_bytes().hash(&mut hasher);
         hasher.finish()
     }
 
-    pub fn compare(&mut self, current_image: &DynamicImage) -> f64 {
+    /// Forget the previous frame, so the next one compares as entirely new.
+    pub fn reset(&mut self) {
+        self.previous_hash = None;
+        self.previous_image_downscaled = None;
+        self.previous_image_full = None;
+        self.previous_lines = None;
+        self.previous_dimensions = None;
+    }
+
+    /// Diff (0.0 = identical) between `current_image` and the previous frame, which it
+    /// then replaces. The full-size path keeps a reference to the frame, not a copy.
+    pub fn compare(&mut self, current_image: &Arc<DynamicImage>) -> f64 {
         self.comparison_count += 1;
 
+        // A resolution change (docking, DPI change) makes the cached frame meaningless:
+        // resized for comparison, a solid or similar screen would look unchanged.
+        let dimensions = current_image.dimensions();
+        if self
+            .previous_dimensions
+            .is_some_and(|previous| previous != dimensions)
+        {
+            tracing::debug!(
+                "Frame size changed {:?} -> {:?}; dropping previous frame",
+                self.previous_dimensions,
+                dimensions
+            );
+            self.reset();
+        }
+        self.previous_dimensions = Some(dimensions);
+
+        // Taken from the unscaled frame: scroll offsets are rarely a multiple of the
+        // downscale factor, and only exact line matches count.
+        let current_lines = self
+            .config
+            .scroll_aware
+            .then(|| LineSignatures::of(&current_image.to_luma8()));
+
+        // Quantizing commutes with nearest-neighbour downscaling, so the downscaled
+        // path quantizes the small image; the full-size path compares a posterized copy.
+        let current_image = match self.config.quantize_bits {
+            Some(bits) if !self.config.downscale_comparison => {
+                Arc::new(posterize(current_image, bits))
+            }
+            _ => Arc::clone(current_image),
+        };
+        let current_image = &current_image;
+
         let current_downscaled = if self.config.downscale_comparison {
-            Some(self.downscale(current_image))
+            Some(self.quantize(self.downscale(current_image)))
         } else {
             None
         };
 
         let current_hash = if self.config.hash_early_exit {
-            let to_hash = current_downscaled.as_ref().unwrap_or(current_image);
+            let to_hash = current_downscaled.as_ref().unwrap_or(&**current_image);
             Some(self.hash_image(to_hash))
         } else {
             None
@@ -86,7 +148,12 @@ impl FrameComparer {
             && self.previous_image_downscaled.is_none()
             && self.previous_image_full.is_none()
         {
-            self.update_previous_internal(current_image, current_downscaled, current_hash);
+            self.update_previous_internal(
+                current_image,
+                current_downscaled,
+                current_hash,
+                current_lines,
+            );
             return 1.0;
         }
 
@@ -103,53 +170,177 @@ impl FrameComparer {
             let prev = self.previous_image_downscaled.as_ref();
             let curr = current_downscaled.as_ref();
             match (prev, curr) {
-                (Some(p), Some(c)) => (p, c.clone()),
+                (Some(p), Some(c)) => (p, c),
                 _ => {
-                    self.update_previous_internal(current_image, current_downscaled, current_hash);
+                    self.update_previous_internal(
+                        current_image,
+                        current_downscaled,
+                        current_hash,
+                        current_lines,
+                    );
                     return 1.0;
                 }
             }
         } else {
             let prev = self.previous_image_full.as_ref();
             match prev {
-                Some(p) => (p, current_image.clone()),
+                Some(p) => (&**p, &**current_image),
                 None => {
-                    self.update_previous_internal(current_image, current_downscaled, current_hash);
+                    self.update_previous_internal(
+                        current_image,
+                        current_downscaled,
+                        current_hash,
+                        current_lines,
+                    );
                     return 1.0;
                 }
             }
         };
 
         let diff = if self.config.single_metric {
-            compare_histogram(prev_img, &curr_img).unwrap_or(1.0)
+            compare_histogram(prev_img, curr_img).unwrap_or(1.0)
         } else {
-            let histogram_diff = compare_histogram(prev_img, &curr_img).unwrap_or(1.0);
+            let histogram_diff = compare_histogram(prev_img, curr_img).unwrap_or(1.0);
             // SSIM omitted for simplicity in port, as single_metric is default TRUE in screenpipe
             histogram_diff
         };
+        let diff = match (&self.previous_lines, &current_lines) {
+            (Some(previous), Some(current)) => {
+                diff * (1.0 - current.explained_by_shift_of(previous))
+            }
+            _ => diff,
+        };
 
-        self.update_previous_internal(current_image, current_downscaled, current_hash);
+        self.update_previous_internal(
+            current_image,
+            current_downscaled,
+            current_hash,
+            current_lines,
+        );
         diff
     }
 
     fn update_previous_internal(
         &mut self,
-        full_image: &DynamicImage,
+        full_image: &Arc<DynamicImage>,
         downscaled: Option<DynamicImage>,
         hash: Option<u64>,
+        lines: Option<LineSignatures>,
     ) {
         self.previous_hash = hash;
+        self.previous_lines = lines;
         if self.config.downscale_comparison {
             self.previous_image_downscaled =
                 downscaled.or_else(|| Some(self.downscale(full_image)));
             self.previous_image_full = None;
         } else {
-            self.previous_image_full = Some(full_image.clone());
+            self.previous_image_full = Some(Arc::clone(full_image));
             self.previous_image_downscaled = None;
         }
     }
 }
 
+/// Largest scroll offset, in lines, that is searched for. Bounds the search on tall
+/// frames; a bigger jump is effectively a new page.
+const MAX_SCROLL_SHIFT: usize = 512;
+
+/// Per-row and per-column hashes of a luma frame, used to detect scrolling.
+/// Uniform lines hash to `None`: they match at any offset and would make every shift
+/// look plausible.
+struct LineSignatures {
+    rows: Vec<Option<u64>>,
+    cols: Vec<Option<u64>>,
+}
+
+impl LineSignatures {
+    /// One pass over the raw luma buffer: rows are hashed as slices, columns are
+    /// folded into running FNV-1a hashes as the rows go by.
+    fn of(luma: &GrayImage) -> Self {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let width = luma.width() as usize;
+        if width == 0 || luma.height() == 0 {
+            return Self {
+                rows: Vec::new(),
+                cols: Vec::new(),
+            };
+        }
+        let pixels = luma.as_raw();
+        let first_row = &pixels[..width];
+        let mut col_hashes = vec![FNV_OFFSET; width];
+        let mut col_uniform = vec![true; width];
+        let rows = pixels
+            .chunks_exact(width)
+            .map(|row| {
+                for (x, &value) in row.iter().enumerate() {
+                    col_hashes[x] = (col_hashes[x] ^ value as u64).wrapping_mul(FNV_PRIME);
+                    col_uniform[x] &= value == first_row[x];
+                }
+                let uniform = row.iter().all(|&value| value == row[0]);
+                (!uniform).then(|| {
+                    let mut hasher = DefaultHasher::new();
+                    row.hash(&mut hasher);
+                    hasher.finish()
+                })
+            })
+            .collect();
+        let cols = col_hashes
+            .into_iter()
+            .zip(col_uniform)
+            .map(|(hash, uniform)| (!uniform).then_some(hash))
+            .collect();
+        Self { rows, cols }
+    }
+
+    /// Fraction (0-1) of this frame's lines that are the previous frame's lines shifted
+    /// by one dominant offset, vertically or horizontally. 0 when no shift beats "no shift".
+    fn explained_by_shift_of(&self, previous: &LineSignatures) -> f64 {
+        dominant_shift_fraction(&previous.rows, &self.rows)
+            .max(dominant_shift_fraction(&previous.cols, &self.cols))
+    }
+}
+
+fn dominant_shift_fraction(previous: &[Option<u64>], current: &[Option<u64>]) -> f64 {
+    let informative = current.iter().filter(|line| line.is_some()).count();
+    if previous.len() != current.len() || informative == 0 {
+        return 0.0;
+    }
+    let matches_at = |shift: isize| {
+        current
+            .iter()
+            .enumerate()
+            .filter(|(i, line)| {
+                let j = *i as isize + shift;
+                line.is_some() && j >= 0 && previous.get(j as usize) == Some(*line)
+            })
+            .count()
+    };
+    let max_shift = (current.len() / 2).min(MAX_SCROLL_SHIFT) as isize;
+    let best = (1..=max_shift)
+        .flat_map(|shift| [shift, -shift])
+        .map(matches_at)
+        .max()
+        .unwrap_or(0);
+    if best > matches_at(0) {
+        best as f64 / informative as f64
+    } else {
+        0.0
+    }
+}
+
+/// Keep the top `bits` bits (clamped to 1-8) of each color channel.
+pub fn posterize(image: &DynamicImage, bits: u8) -> DynamicImage {
+    let mask = (0xFFu32 << (8 - bits.clamp(1, 8))) as u8;
+    let mut rgba = image.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel &= mask;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
 pub fn compare_histogram(image1: &DynamicImage, image2: &DynamicImage) -> anyhow::Result<f64> {
     let image_one = image1.to_luma8();
     let mut image_two = image2.to_luma8();
@@ -164,3 +355,136 @@ pub fn compare_histogram(image1: &DynamicImage, image2: &DynamicImage) -> anyhow
     image_compare::gray_similarity_histogram(Metric::Hellinger, &image_one, &image_two)
         .map_err(|e| anyhow::anyhow!("Failed to compare images: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn lsb_noise_frames() -> (DynamicImage, DynamicImage) {
+        let clean = DynamicImage::ImageLuma8(GrayImage::from_pixel(96, 96, Luma([100])));
+        let noisy = DynamicImage::ImageLuma8(GrayImage::from_fn(96, 96, |x, y| {
+            Luma([if (x + y) % 2 == 0 { 100 } else { 101 }])
+        }));
+        (clean, noisy)
+    }
+
+    fn diff_of(config: FrameComparisonConfig) -> f64 {
+        let (clean, noisy) = lsb_noise_frames();
+        let mut comparer = FrameComparer::new(config);
+        comparer.compare(&Arc::new(clean));
+        comparer.compare(&Arc::new(noisy))
+    }
+
+    #[test]
+    fn quantization_absorbs_lsb_noise() {
+        let without = diff_of(FrameComparisonConfig {
+            downscale_factor: 1,
+            ..Default::default()
+        });
+        let with = diff_of(FrameComparisonConfig {
+            downscale_factor: 1,
+            quantize_bits: Some(4),
+            ..Default::default()
+        });
+        let full_size = diff_of(FrameComparisonConfig {
+            downscale_comparison: false,
+            quantize_bits: Some(4),
+            ..Default::default()
+        });
+
+        assert!(
+            without > 0.01,
+            "noise should register without quantization: {without}"
+        );
+        assert_eq!(with, 0.0);
+        assert_eq!(full_size, 0.0);
+    }
+
+    #[test]
+    fn resolution_change_counts_as_a_new_frame() {
+        let solid = |w, h| {
+            Arc::new(DynamicImage::ImageLuma8(GrayImage::from_pixel(
+                w,
+                h,
+                Luma([90]),
+            )))
+        };
+        for scroll_aware in [false, true] {
+            let mut comparer = FrameComparer::new(FrameComparisonConfig {
+                scroll_aware,
+                ..Default::default()
+            });
+            assert_eq!(comparer.compare(&solid(1920, 1080)), 1.0);
+            assert_eq!(comparer.compare(&solid(1920, 1080)), 0.0);
+            assert_eq!(comparer.compare(&solid(2560, 1440)), 1.0);
+            assert_eq!(comparer.compare(&solid(2560, 1440)), 0.0);
+        }
+    }
+
+    #[test]
+    fn scroll_aware_diff_discounts_vertical_shift() {
+        let page = |y: u32, x: u32| ((y * 37 + x * 11 + (x * y) % 7) % 200) as u8;
+        let top = DynamicImage::ImageLuma8(GrayImage::from_fn(120, 240, |x, y| Luma([page(y, x)])));
+        // Scrolled down by 60 rows; the newly exposed strip is brighter content.
+        let scrolled = DynamicImage::ImageLuma8(GrayImage::from_fn(120, 240, |x, y| {
+            if y < 180 {
+                Luma([page(y + 60, x)])
+            } else {
+                Luma([200 + ((x + y) % 56) as u8])
+            }
+        }));
+        let (top, scrolled) = (Arc::new(top), Arc::new(scrolled));
+
+        let diff_for = |scroll_aware| {
+            let mut comparer = FrameComparer::new(FrameComparisonConfig {
+                scroll_aware,
+                ..Default::default()
+            });
+            comparer.compare(&top);
+            comparer.compare(&scrolled)
+        };
+        let naive = diff_for(false);
+        let aware = diff_for(true);
+
+        assert!(
+            naive > 0.0,
+            "scroll should register without shift detection"
+        );
+        assert!(aware < naive * 0.5, "aware {aware} vs naive {naive}");
+    }
+
+    #[test]
+    fn full_size_comparison_shares_frames_instead_of_copying() {
+        let frame = |value| {
+            Arc::new(DynamicImage::ImageLuma8(GrayImage::from_pixel(
+                64,
+                64,
+                Luma([value]),
+            )))
+        };
+        let first = frame(10);
+        let second = frame(200);
+
+        let mut comparer = FrameComparer::new(FrameComparisonConfig {
+            downscale_comparison: false,
+            ..Default::default()
+        });
+        comparer.compare(&first);
+        // The comparer holds the caller's buffer, not a copy of it.
+        assert_eq!(Arc::strong_count(&first), 2);
+        assert!(Arc::ptr_eq(
+            comparer.previous_image_full.as_ref().unwrap(),
+            &first
+        ));
+
+        comparer.compare(&second);
+        assert_eq!(Arc::strong_count(&first), 1);
+        assert_eq!(Arc::strong_count(&second), 2);
+
+        // The downscaled path keeps only its small copy.
+        let mut downscaled = FrameComparer::new(FrameComparisonConfig::default());
+        downscaled.compare(&first);
+        assert_eq!(Arc::strong_count(&first), 1);
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+/// Time since the user last touched keyboard or mouse.
+pub trait IdleSource: Send + Sync + std::fmt::Debug {
+    /// `None` when idle time can't be determined; capture then never counts as idle.
+    fn idle_for(&self) -> Option<Duration>;
+}
+
+/// Reads last-input time from the operating system.
+///
+/// Only implemented on Windows with the `idle-detection` feature (`GetLastInputInfo`);
+/// elsewhere idle time is unknown and capture runs as usual.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemIdleSource;
+
+impl IdleSource for SystemIdleSource {
+    #[cfg(all(windows, feature = "idle-detection"))]
+    fn idle_for(&self) -> Option<Duration> {
+        use windows_sys::Win32::System::SystemInformation::GetTickCount;
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // SAFETY: `info` is a valid, correctly sized LASTINPUTINFO for the duration of the call.
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // Both are 32-bit tick counts; wrapping_sub handles the 49.7-day rollover.
+        // SAFETY: GetTickCount takes no arguments and has no preconditions.
+        let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        Some(Duration::from_millis(idle_ms as u64))
+    }
+
+    #[cfg(not(all(windows, feature = "idle-detection")))]
+    fn idle_for(&self) -> Option<Duration> {
+        use std::sync::Once;
+
+        static WARN_ONCE: Once = Once::new();
+        WARN_ONCE.call_once(|| {
+            tracing::warn!("idle detection is not available in this build; capturing regardless")
+        });
+        None
+    }
+}
+
+/// What the capture task should do this tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleDecision {
+    /// Capture as usual. `resumed` is set on the first tick after an idle period, so
+    /// the frame can bypass in-memory dedup and the timeline picks up immediately.
+    Capture { resumed: bool },
+    /// The user is idle: skip capture (and the frame compare) entirely.
+    Skip,
+}
+
+/// Tracks idle/active transitions for one capture task.
+pub struct IdleGate {
+    timeout: Duration,
+    idle: bool,
+    frames_skipped: u64,
+}
+
+impl IdleGate {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            idle: false,
+            frames_skipped: 0,
+        }
+    }
+
+    pub fn observe(&mut self, idle_for: Option<Duration>) -> IdleDecision {
+        let idle_now = idle_for.is_some_and(|idle| idle > self.timeout);
+        let was_idle = std::mem::replace(&mut self.idle, idle_now);
+        if idle_now {
+            self.frames_skipped += 1;
+            IdleDecision::Skip
+        } else {
+            IdleDecision::Capture { resumed: was_idle }
+        }
+    }
+
+    /// Ticks skipped because the user was idle, over the task's lifetime.
+    pub fn frames_skipped(&self) -> u64 {
+        self.frames_skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_gate_skips_while_idle_and_flags_resume() {
+        let mut gate = IdleGate::new(Duration::from_secs(300));
+        let secs = |s| Some(Duration::from_secs(s));
+
+        assert_eq!(
+            gate.observe(secs(10)),
+            IdleDecision::Capture { resumed: false }
+        );
+        assert_eq!(gate.observe(None), IdleDecision::Capture { resumed: false });
+        assert_eq!(gate.observe(secs(301)), IdleDecision::Skip);
+        assert_eq!(gate.observe(secs(400)), IdleDecision::Skip);
+        assert_eq!(
+            gate.observe(secs(1)),
+            IdleDecision::Capture { resumed: true }
+        );
+        assert_eq!(
+            gate.observe(secs(2)),
+            IdleDecision::Capture { resumed: false }
+        );
+        assert_eq!(gate.frames_skipped(), 2);
+    }
+}
@@ -1,3 +1,17 @@
+pub mod analysis;
+pub mod config;
+pub mod control;
+pub mod decode;
+pub mod dedup;
+pub mod dedup_policy;
+pub mod deployment;
 pub mod frame_comparer;
+pub mod idle;
 pub mod monitor;
+pub mod ocr;
 pub mod pipeline;
+pub mod power;
+pub mod privacy;
+pub mod supervisor;
+pub mod units;
+pub mod window_context;
@@ -1,6 +1,7 @@
 use anyhow::{Error, Result};
 use image::DynamicImage;
 use std::fmt;
+use std::future::Future;
 use std::sync::Arc;
 use xcap::Monitor as XcapMonitor;
 
@@ -25,7 +26,7 @@ impl fmt::Display for MonitorListError {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct SafeMonitor {
     monitor_id: u32,
     monitor_data: Arc<MonitorData>,
@@ -41,17 +42,49 @@ pub struct MonitorData {
     pub is_primary: bool,
 }
 
-impl SafeMonitor {
-    pub fn new(monitor: XcapMonitor) -> Self {
-        let monitor_id = monitor.id().unwrap_or(0);
-        let monitor_data = Arc::new(MonitorData {
+impl MonitorData {
+    fn from_xcap(monitor: &XcapMonitor) -> Self {
+        Self {
             width: monitor.width().unwrap_or(0),
             height: monitor.height().unwrap_or(0),
             x: monitor.x().unwrap_or(0),
             y: monitor.y().unwrap_or(0),
             name: monitor.name().unwrap_or_default().to_string(),
             is_primary: monitor.is_primary().unwrap_or(false),
-        });
+        }
+    }
+
+    /// Same physical display: name and geometry match (the primary flag may move).
+    fn same_display(&self, other: &MonitorData) -> bool {
+        self.name == other.name
+            && self.width == other.width
+            && self.height == other.height
+            && self.x == other.x
+            && self.y == other.y
+    }
+}
+
+/// Find `monitor_id` among the live monitors, falling back to a unique name+geometry
+/// match when the id no longer exists (some platforms reassign ids after sleep/wake).
+fn resolve_monitor_id(
+    monitor_id: u32,
+    known: &MonitorData,
+    live: &[(u32, MonitorData)],
+) -> Option<u32> {
+    if live.iter().any(|(id, _)| *id == monitor_id) {
+        return Some(monitor_id);
+    }
+    let mut matches = live.iter().filter(|(_, data)| data.same_display(known));
+    match (matches.next(), matches.next()) {
+        (Some((id, _)), None) => Some(*id),
+        _ => None,
+    }
+}
+
+impl SafeMonitor {
+    pub fn new(monitor: XcapMonitor) -> Self {
+        let monitor_id = monitor.id().unwrap_or(0);
+        let monitor_data = Arc::new(MonitorData::from_xcap(&monitor));
 
         Self {
             monitor_id,
@@ -87,28 +120,40 @@ impl SafeMonitor {
     }
 
     /// Refresh monitor metadata.
+    /// If the id has disappeared, re-resolve it by name and geometry and adopt the new id.
     pub async fn refresh(&mut self) -> Result<()> {
         let monitor_id = self.monitor_id;
+        let known = Arc::clone(&self.monitor_data);
 
-        let refreshed = tokio::task::spawn_blocking(move || -> Result<MonitorData> {
-            let monitors = XcapMonitor::all().map_err(|e| Error::msg(e.to_string()))?;
-            let monitor = monitors
-                .into_iter()
-                .find(|m| m.id().unwrap_or(0) == monitor_id)
-                .ok_or_else(|| anyhow::anyhow!("Monitor {} not found during refresh", monitor_id))?;
-
-            Ok(MonitorData {
-                width: monitor.width().unwrap_or(0),
-                height: monitor.height().unwrap_or(0),
-                x: monitor.x().unwrap_or(0),
-                y: monitor.y().unwrap_or(0),
-                name: monitor.name().unwrap_or_default().to_string(),
-                is_primary: monitor.is_primary().unwrap_or(false),
+        let (resolved_id, refreshed) =
+            tokio::task::spawn_blocking(move || -> Result<(u32, MonitorData)> {
+                let monitors = XcapMonitor::all().map_err(|e| Error::msg(e.to_string()))?;
+                let live: Vec<(u32, MonitorData)> = monitors
+                    .iter()
+                    .map(|m| (m.id().unwrap_or(0), MonitorData::from_xcap(m)))
+                    .collect();
+                let resolved_id =
+                    resolve_monitor_id(monitor_id, &known, &live).ok_or_else(|| {
+                        anyhow::anyhow!("Monitor {} not found during refresh", monitor_id)
+                    })?;
+                live.into_iter()
+                    .find(|(id, _)| *id == resolved_id)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Monitor {} vanished during refresh", resolved_id)
+                    })
             })
-        })
-        .await
-        .map_err(|e| anyhow::anyhow!("refresh task panicked: {}", e))??;
+            .await
+            .map_err(|e| anyhow::anyhow!("refresh task panicked: {}", e))??;
 
+        if resolved_id != self.monitor_id {
+            tracing::info!(
+                "Monitor '{}' changed id {} -> {}",
+                refreshed.name,
+                self.monitor_id,
+                resolved_id
+            );
+            self.monitor_id = resolved_id;
+        }
         self.monitor_data = Arc::new(refreshed);
         tracing::debug!("Refreshed monitor {} metadata", self.monitor_id);
         Ok(())
@@ -121,6 +166,53 @@ impl SafeMonitor {
     pub fn name(&self) -> &str {
         &self.monitor_data.name
     }
+
+    /// A 2560x1440 monitor that was never enumerated, for tests.
+    #[cfg(test)]
+    pub(crate) fn stub(monitor_id: u32, name: &str) -> Self {
+        Self {
+            monitor_id,
+            monitor_data: Arc::new(MonitorData {
+                width: 2560,
+                height: 1440,
+                x: 0,
+                y: 0,
+                name: name.to_string(),
+                is_primary: false,
+            }),
+        }
+    }
+}
+
+/// What the capture loop needs from a display. [`SafeMonitor`] is the real one; tests
+/// drive the loop with a fake.
+pub trait FrameSource: Send + Sync {
+    fn id(&self) -> u32;
+
+    fn name(&self) -> &str;
+
+    fn capture_image(&self) -> impl Future<Output = Result<DynamicImage>> + Send;
+
+    /// May change [`FrameSource::id`] when the display was renumbered.
+    fn refresh(&mut self) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl FrameSource for SafeMonitor {
+    fn id(&self) -> u32 {
+        SafeMonitor::id(self)
+    }
+
+    fn name(&self) -> &str {
+        SafeMonitor::name(self)
+    }
+
+    async fn capture_image(&self) -> Result<DynamicImage> {
+        SafeMonitor::capture_image(self).await
+    }
+
+    async fn refresh(&mut self) -> Result<()> {
+        SafeMonitor::refresh(self).await
+    }
 }
 
 pub async fn list_monitors() -> Vec<SafeMonitor> {
@@ -149,3 +241,142 @@ pub async fn get_monitor_by_id(id: u32) -> Option<SafeMonitor> {
     .await
     .unwrap_or(None)
 }
+
+/// Whether `selector` names this monitor. A number only ever matches the id, so "2"
+/// does not select "DELL U2720Q"; anything else is a case-insensitive name substring.
+fn selector_matches(selector: &str, monitor: &SafeMonitor) -> bool {
+    let selector = selector.trim();
+    match selector.parse::<u32>() {
+        Ok(id) => id == monitor.id(),
+        Err(_) => monitor
+            .name()
+            .to_lowercase()
+            .contains(&selector.to_lowercase()),
+    }
+}
+
+/// Apply `--monitor` / `--exclude-monitor` style filters to the monitor list.
+///
+/// An empty `include` keeps every monitor; `exclude` always wins. Skipped monitors are
+/// logged with the reason, and a filter that leaves nothing is an error naming the
+/// available monitors.
+pub fn filter_monitors(
+    monitors: Vec<SafeMonitor>,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<SafeMonitor>> {
+    let available: Vec<String> = monitors
+        .iter()
+        .map(|m| format!("{} ({})", m.id(), m.name()))
+        .collect();
+
+    let selected: Vec<SafeMonitor> = monitors
+        .into_iter()
+        .filter(|monitor| {
+            if let Some(selector) = exclude.iter().find(|s| selector_matches(s, monitor)) {
+                tracing::info!(
+                    "Skipping monitor {} ({}): excluded by '{}'",
+                    monitor.id(),
+                    monitor.name(),
+                    selector
+                );
+                return false;
+            }
+            if !include.is_empty() && !include.iter().any(|s| selector_matches(s, monitor)) {
+                tracing::info!(
+                    "Skipping monitor {} ({}): not selected by --monitor",
+                    monitor.id(),
+                    monitor.name()
+                );
+                return false;
+            }
+            true
+        })
+        .collect();
+
+    if selected.is_empty() {
+        anyhow::bail!(
+            "Monitor filter matched nothing. Available monitors: {}",
+            available.join(", ")
+        );
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(name: &str, x: i32) -> MonitorData {
+        MonitorData {
+            width: 2560,
+            height: 1440,
+            x,
+            y: 0,
+            name: name.to_string(),
+            is_primary: false,
+        }
+    }
+
+    #[test]
+    fn resolves_renumbered_monitor_by_name_and_geometry() {
+        let known = display("DELL U2720Q", 0);
+        let after_wake = vec![
+            (41, display("DELL U2720Q", 0)),
+            (42, display("LG HDR", 2560)),
+        ];
+
+        assert_eq!(resolve_monitor_id(7, &known, &after_wake), Some(41));
+        // A stable id is kept as-is.
+        assert_eq!(
+            resolve_monitor_id(42, &display("LG HDR", 2560), &after_wake),
+            Some(42)
+        );
+    }
+
+    #[test]
+    fn ambiguous_or_missing_display_is_not_resolved() {
+        let known = display("Generic PnP", 0);
+        let twins = vec![
+            (1, display("Generic PnP", 0)),
+            (2, display("Generic PnP", 0)),
+        ];
+        let moved = vec![(3, display("Generic PnP", 1920))];
+
+        assert_eq!(resolve_monitor_id(9, &known, &twins), None);
+        assert_eq!(resolve_monitor_id(9, &known, &moved), None);
+    }
+
+    #[test]
+    fn filter_monitors_by_id_or_name_substring() {
+        let monitors = || {
+            [(1, "DELL U2720Q"), (2, "LG HDR"), (3, "Built-in Retina")]
+                .into_iter()
+                .map(|(id, name)| SafeMonitor::stub(id, name))
+                .collect::<Vec<_>>()
+        };
+        let ids = |selected: Vec<SafeMonitor>| selected.iter().map(|m| m.id()).collect::<Vec<_>>();
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(filter_monitors(monitors(), &[], &[]).unwrap()),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            ids(filter_monitors(monitors(), &args(&["dell", "3"]), &[]).unwrap()),
+            [1, 3]
+        );
+        assert_eq!(
+            ids(filter_monitors(monitors(), &[], &args(&["retina"])).unwrap()),
+            [1, 2]
+        );
+        // Numbers are ids only: "2" must not also exclude "DELL U2720Q".
+        assert_eq!(
+            ids(filter_monitors(monitors(), &[], &args(&["2"])).unwrap()),
+            [1, 3]
+        );
+
+        let err = filter_monitors(monitors(), &args(&["hdr"]), &args(&["2"])).unwrap_err();
+        assert!(err.to_string().contains("1 (DELL U2720Q)"), "{err}");
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use image::DynamicImage;
+use std::future::Future;
+
+/// Text recognised in one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrResult {
+    pub text: String,
+    /// Engine confidence, 0-1.
+    pub confidence: f64,
+    /// BCP 47 tag when the engine detects the language.
+    pub language: Option<String>,
+    /// Word/line boxes in the engine's JSON shape, stored as-is alongside the text.
+    pub bbox: Option<serde_json::Value>,
+}
+
+/// An OCR backend. Implementations must be cheap to share across tasks.
+pub trait OcrEngine: Send + Sync {
+    fn recognize(&self, image: &DynamicImage) -> impl Future<Output = Result<OcrResult>> + Send;
+}
+
+/// Engine that recognises nothing, for tests and for running the pipeline without OCR.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopOcrEngine;
+
+impl OcrEngine for NoopOcrEngine {
+    async fn recognize(&self, _image: &DynamicImage) -> Result<OcrResult> {
+        Ok(OcrResult {
+            text: String::new(),
+            confidence: 0.0,
+            language: None,
+            bbox: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[tokio::test]
+    async fn noop_engine_returns_empty_text() {
+        let frame = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([0])));
+        let result = NoopOcrEngine.recognize(&frame).await.unwrap();
+        assert!(result.text.is_empty());
+        assert_eq!(result.confidence, 0.0);
+    }
+}
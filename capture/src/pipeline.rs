@@ -1,59 +1,655 @@
+use crate::analysis::{passes_text_gate, DEFAULT_OCR_TEXT_GATE_THRESHOLD};
+use crate::config::Config;
+use crate::control::{wait_while_paused, ControlMessage};
+use crate::dedup::{validate_dedup_threshold, HashAlgorithm, DEFAULT_DEDUP_THRESHOLD};
+use crate::dedup_policy::{
+    DedupDecision, DedupPolicy, DedupPolicyParams, DedupPolicyRegistry, FrameContext, KeptFrame,
+    DEFAULT_POLICY,
+};
 use crate::frame_comparer::{FrameComparer, FrameComparisonConfig};
-use crate::monitor::{get_monitor_by_id, SafeMonitor};
+use crate::idle::{IdleDecision, IdleGate, IdleSource, SystemIdleSource};
+use crate::monitor::{get_monitor_by_id, FrameSource};
+use crate::power::{PowerPolicy, PowerSource, SystemPowerSource};
+use crate::privacy::PrivacyBlocklist;
+use crate::supervisor::supervise;
+use crate::window_context::{focused_window_from, SystemWindowSource, WindowContext, WindowSource};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use image::DynamicImage;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub struct CaptureEvent {
-    pub image: DynamicImage,
+    /// Shared through an `Arc`, so handing the frame on never copies the pixels.
+    pub image: Arc<DynamicImage>,
     pub timestamp: DateTime<Utc>,
     pub frame_number: u64,
+    /// Focused window when the frame was kept; `None` if it couldn't be resolved.
+    pub window: Option<WindowContext>,
+    /// Earlier frames of the same typing burst this one replaced (see [`TextDebounce`]).
+    pub merged_frames: u32,
+    /// Passed the cheap text gate ([`passes_text_gate`]); OCR skips frames without it.
+    pub likely_text: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CaptureConfig {
+    /// When set, every captured frame is written here as PNG (before dedup) for debugging.
+    pub debug_dump_dir: Option<PathBuf>,
+    /// Temporarily raise the capture rate while the screen is changing quickly.
+    pub burst: Option<BurstConfig>,
+    /// Posterize frames to this many bits per channel before comparison (not storage).
+    pub dedup_quantize_bits: Option<u8>,
+    /// Frame diff below which frames are dropped in memory. Defaults to
+    /// [`DEFAULT_DEDUP_THRESHOLD`]; raise it for high-motion screens, lower it for subtle text.
+    pub dedup_threshold: Option<f64>,
+    /// Treat frames that are mostly a scrolled copy of the previous one as less changed.
+    /// Off by default.
+    pub scroll_aware_dedup: Option<bool>,
+    /// Slow down or pause capture while running on battery.
+    pub power: Option<PowerPolicy>,
+    /// Where `power` reads the power state. Defaults to the system's, cached.
+    pub power_source: Option<Arc<dyn PowerSource>>,
+    /// Pause/resume without stopping the task. Comparer state survives a pause.
+    pub control: Option<tokio::sync::watch::Receiver<ControlMessage>>,
+    /// Skip capture while there has been no user input for this long.
+    pub idle_timeout: Option<Duration>,
+    /// Where `idle_timeout` reads the time since last input. Defaults to the system's.
+    pub idle_source: Option<Arc<dyn IdleSource>>,
+    /// Drop frames while a blocklisted app or window title is focused.
+    pub privacy: Option<PrivacyBlocklist>,
+    /// With `privacy` set, also drop frames while the focused window is unknown.
+    /// Defaults to recording them.
+    pub privacy_fail_closed: Option<bool>,
+    /// Where the focused window is looked up. Defaults to the system's.
+    pub window_source: Option<Arc<dyn WindowSource>>,
+    /// Slow down while the screen stays static.
+    pub idle_backoff: Option<IdleBackoffConfig>,
+    /// Perceptual hash computed per frame. Defaults to [`HashAlgorithm::Average`].
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// Registered name of the dedup policy. Defaults to the threshold policy.
+    pub dedup_policy: Option<String>,
+    /// Where `dedup_policy` is looked up. Defaults to the built-in policies only.
+    pub dedup_policies: Option<Arc<DedupPolicyRegistry>>,
+    /// Hold frames with small changes (typing) and store only the last one once the
+    /// screen has been settled this long.
+    pub text_debounce: Option<Duration>,
+    /// Edge density at or above which a kept frame counts as `likely_text`. Defaults to
+    /// [`DEFAULT_OCR_TEXT_GATE_THRESHOLD`].
+    pub ocr_text_gate_threshold: Option<f64>,
+    /// Receives every stored frame with its monitor id, so tests can check what storage saw.
+    #[cfg(test)]
+    pub(crate) frame_sink: Option<tokio::sync::mpsc::UnboundedSender<(u32, CaptureEvent)>>,
+}
+
+/// How often each capture task logs the frame rate it actually achieved.
+const ACHIEVED_FPS_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often a task paused for low battery re-checks the power state.
+const POWER_PAUSE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Kept frames below this diff count as small text edits for [`TextDebounce`].
+const TEXT_DEBOUNCE_MAX_DIFF: f64 = 0.05;
+
+/// Consecutive frames at or above `trigger_diff` needed to enter burst mode.
+const BURST_TRIGGER_FRAMES: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct BurstConfig {
+    /// Frame diff at or above which a frame counts as "active".
+    pub trigger_diff: f64,
+    /// Capture rate while bursting. Only ever speeds capture up.
+    pub burst_fps: f64,
+    /// How long burst mode lasts after the last active frame.
+    pub cooldown_secs: u64,
+}
+
+impl Default for BurstConfig {
+    fn default() -> Self {
+        Self {
+            trigger_diff: 0.05,
+            burst_fps: 4.0,
+            cooldown_secs: 5,
+        }
+    }
+}
+
+/// Tracks whether the capture task is in burst mode and picks the next tick interval.
+pub struct BurstState {
+    config: BurstConfig,
+    consecutive_active: u32,
+    burst_until: Option<Instant>,
+}
+
+impl BurstState {
+    pub fn new(config: BurstConfig) -> Self {
+        Self {
+            config,
+            consecutive_active: 0,
+            burst_until: None,
+        }
+    }
+
+    pub fn in_burst(&self, now: Instant) -> bool {
+        self.burst_until.is_some_and(|until| now < until)
+    }
+
+    /// Record one frame's diff and return the interval to wait before the next capture.
+    pub fn observe(&mut self, diff: f64, now: Instant, base_interval: Duration) -> Duration {
+        let was_bursting = self.in_burst(now);
+
+        if diff >= self.config.trigger_diff {
+            self.consecutive_active += 1;
+            if self.consecutive_active >= BURST_TRIGGER_FRAMES {
+                self.burst_until = Some(now + Duration::from_secs(self.config.cooldown_secs));
+            }
+        } else {
+            self.consecutive_active = 0;
+        }
+
+        let bursting = self.in_burst(now);
+        if bursting && !was_bursting {
+            info!("Entering burst mode ({} fps)", self.config.burst_fps);
+        } else if !bursting && was_bursting {
+            info!("Leaving burst mode");
+        }
+
+        match Duration::try_from_secs_f64(1.0 / self.config.burst_fps) {
+            Ok(burst_interval) if bursting => burst_interval.min(base_interval),
+            _ => base_interval,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IdleBackoffConfig {
+    /// Seconds without a changed frame before the interval starts widening.
+    pub max_inactive_secs: u64,
+    /// Cap on the backoff, as a multiple of the base interval.
+    pub max_backoff_factor: u32,
+}
+
+impl Default for IdleBackoffConfig {
+    fn default() -> Self {
+        Self {
+            max_inactive_secs: 10,
+            max_backoff_factor: 8,
+        }
+    }
+}
+
+/// Widens the tick interval while frames keep getting deduplicated, doubling each tick
+/// up to `max_backoff_factor`, and drops back to the base rate on the first changed frame.
+/// A widened wait never runs past the next heartbeat keyframe.
+pub struct IdleBackoff {
+    config: IdleBackoffConfig,
+    last_change: Instant,
+    factor: u32,
+}
+
+impl IdleBackoff {
+    pub fn new(config: IdleBackoffConfig, now: Instant) -> Self {
+        Self {
+            config,
+            last_change: now,
+            factor: 1,
+        }
+    }
+
+    /// Record whether this frame changed and return the interval to wait before the next
+    /// capture: at most `until_heartbeat`, but never less than `base_interval`.
+    pub fn observe(
+        &mut self,
+        changed: bool,
+        now: Instant,
+        base_interval: Duration,
+        until_heartbeat: Duration,
+    ) -> Duration {
+        if changed {
+            if self.factor > 1 {
+                info!("Leaving idle backoff");
+            }
+            self.factor = 1;
+            self.last_change = now;
+        } else if now.duration_since(self.last_change)
+            >= Duration::from_secs(self.config.max_inactive_secs)
+        {
+            if self.factor == 1 {
+                info!("Screen static, backing off capture");
+            }
+            self.factor = self
+                .factor
+                .saturating_mul(2)
+                .min(self.config.max_backoff_factor.max(1));
+        }
+        (base_interval * self.factor).min(until_heartbeat.max(base_interval))
+    }
+}
+
+/// Collapses a run of small changes (a typing burst) into its final frame. Frames come
+/// back paired with how many earlier frames of their burst they replaced.
+pub struct TextDebounce<T> {
+    settle: Duration,
+    max_hold: Duration,
+    pending: Option<T>,
+    merged: u32,
+    held_since: Option<Instant>,
+    last_change: Option<Instant>,
+}
+
+impl<T> TextDebounce<T> {
+    /// `max_hold` bounds how long one burst can be held while typing never settles.
+    pub fn new(settle: Duration, max_hold: Duration) -> Self {
+        Self {
+            settle,
+            max_hold,
+            pending: None,
+            merged: 0,
+            held_since: None,
+            last_change: None,
+        }
+    }
+
+    /// Offer a kept frame and return the frames to store now. A small change replaces
+    /// the pending frame; anything else flushes it and is stored right after it.
+    pub fn offer(&mut self, frame: T, small_change: bool, now: Instant) -> Vec<(T, u32)> {
+        if small_change {
+            if self.pending.replace(frame).is_some() {
+                self.merged += 1;
+            } else {
+                self.held_since = Some(now);
+            }
+            self.last_change = Some(now);
+            return Vec::new();
+        }
+        self.flush().into_iter().chain([(frame, 0)]).collect()
+    }
+
+    /// The pending frame, once no small change has arrived for the settle time or it
+    /// has been held for `max_hold`.
+    pub fn poll(&mut self, now: Instant) -> Option<(T, u32)> {
+        let settled = self
+            .last_change
+            .is_some_and(|at| now.duration_since(at) >= self.settle);
+        let expired = self
+            .held_since
+            .is_some_and(|at| now.duration_since(at) >= self.max_hold);
+        if !(settled || expired) {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// The pending frame, unconditionally. Used when capture stops.
+    pub fn flush(&mut self) -> Option<(T, u32)> {
+        self.last_change = None;
+        self.held_since = None;
+        let merged = std::mem::take(&mut self.merged);
+        self.pending.take().map(|frame| (frame, merged))
+    }
+}
+
+/// The per-frame dedup step: asks the policy about each frame (which emits the dedup
+/// decision event) and remembers when and what was last kept.
+struct DedupStep {
+    policy: Box<dyn DedupPolicy>,
+    hash_algorithm: HashAlgorithm,
+    /// The policy's heartbeat: a frame is kept at least this often.
+    max_skip: Duration,
+    last_kept: Instant,
+    last_kept_frame: Option<KeptFrame>,
+}
+
+impl DedupStep {
+    fn new(
+        policy: Box<dyn DedupPolicy>,
+        hash_algorithm: HashAlgorithm,
+        max_skip: Duration,
+        now: Instant,
+    ) -> Self {
+        Self {
+            policy,
+            hash_algorithm,
+            max_skip,
+            last_kept: now,
+            last_kept_frame: None,
+        }
+    }
+
+    fn decide(
+        &mut self,
+        image: &Arc<DynamicImage>,
+        monitor_id: u32,
+        diff: f64,
+        resumed_from_idle: bool,
+        window: Option<&WindowContext>,
+        now: Instant,
+    ) -> DedupDecision {
+        let mut ctx = FrameContext::new(image, monitor_id, diff, self.hash_algorithm);
+        ctx.since_last_kept = now.saturating_duration_since(self.last_kept);
+        ctx.resumed_from_idle = resumed_from_idle;
+        ctx.window = window;
+        ctx.previous = self.last_kept_frame.as_ref();
+        let decision = self.policy.should_store(&ctx);
+        if decision == DedupDecision::Kept {
+            self.last_kept = now;
+            self.last_kept_frame = Some(ctx.into_kept(Arc::clone(image)));
+        }
+        decision
+    }
+
+    /// Time left before the heartbeat keeps a frame regardless of change.
+    fn until_heartbeat(&self, now: Instant) -> Duration {
+        self.max_skip
+            .saturating_sub(now.saturating_duration_since(self.last_kept))
+    }
+}
+
+/// Counters behind the periodic per-monitor achieved-rate log line.
+struct RateReport {
+    every: Duration,
+    started: Instant,
+    frames: u64,
+    kept: u64,
+    deduped: u64,
+    /// Time spent paused (control channel or low battery) over the task's lifetime.
+    paused_total: Duration,
+}
+
+impl RateReport {
+    fn new(every: Duration, now: Instant) -> Self {
+        Self {
+            every,
+            started: now,
+            frames: 0,
+            kept: 0,
+            deduped: 0,
+            paused_total: Duration::ZERO,
+        }
+    }
+
+    /// Start a new measurement window, e.g. after a pause.
+    fn restart(&mut self, now: Instant) {
+        self.started = now;
+        self.frames = 0;
+        self.kept = 0;
+        self.deduped = 0;
+    }
+
+    /// Log the achieved rate once per `every`, then start a new window.
+    fn report_if_due(&mut self, monitor_id: u32, configured_fps: f64, paused: bool, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed < self.every {
+            return;
+        }
+        info!(
+            "Monitor {} achieved {:.2} fps (configured {:.2}); {} kept, {} deduped in memory; paused={} ({:.0}s paused in total)",
+            monitor_id,
+            self.frames as f64 / elapsed.as_secs_f64(),
+            configured_fps,
+            self.kept,
+            self.deduped,
+            paused,
+            self.paused_total.as_secs_f64()
+        );
+        self.restart(now);
+    }
+}
+
+/// Wait out a control-channel pause, still logging the rate line (flagged as paused)
+/// on schedule. Returns how long capture was paused, `None` if it wasn't, or an error
+/// when the channel closed.
+async fn wait_out_pause(
+    control: &mut tokio::sync::watch::Receiver<ControlMessage>,
+    rate: &mut RateReport,
+    monitor_id: u32,
+    configured_fps: f64,
+) -> Result<Option<Duration>, tokio::sync::watch::error::RecvError> {
+    let paused_at = Instant::now();
+    let mut counted = Duration::ZERO;
+    let mut paused = false;
+    loop {
+        let waited = tokio::time::timeout(rate.every, wait_while_paused(control)).await;
+        paused |= !matches!(waited, Ok(Ok(None)));
+        if paused {
+            let so_far = paused_at.elapsed();
+            rate.paused_total += so_far - counted;
+            counted = so_far;
+        }
+        match waited {
+            Ok(result) => {
+                result?;
+                return Ok(paused.then_some(counted));
+            }
+            Err(_) => rate.report_if_due(monitor_id, configured_fps, true, Instant::now()),
+        }
+    }
 }
 
 pub async fn continuous_capture(
     monitor_id: u32,
     interval: Duration,
+    config: CaptureConfig,
 ) -> Result<()> {
+    let monitor = match get_monitor_by_id(monitor_id).await {
+        Some(m) => m,
+        None => {
+            error!("Monitor {} not found", monitor_id);
+            return Err(anyhow::anyhow!("Monitor not found"));
+        }
+    };
+    capture_loop(monitor, interval, config).await
+}
+
+/// The capture loop behind [`continuous_capture`], for any [`FrameSource`].
+async fn capture_loop<M: FrameSource>(
+    mut monitor: M,
+    interval: Duration,
+    config: CaptureConfig,
+) -> Result<()> {
+    let mut monitor_id = monitor.id();
+    let skip_threshold =
+        validate_dedup_threshold(config.dedup_threshold.unwrap_or(DEFAULT_DEDUP_THRESHOLD))?;
     let mut frame_counter: u64 = 0;
     
     // Default config: downscale by 4, hash early exit enabled
     let mut frame_comparer = FrameComparer::new(FrameComparisonConfig {
         downscale_factor: 4,
+        quantize_bits: config.dedup_quantize_bits,
+        scroll_aware: config.scroll_aware_dedup.unwrap_or(false),
         ..Default::default()
     });
 
-    let max_skip_duration = Duration::from_secs(10);
-    let mut last_capture_time = Instant::now();
-
-    info!("Starting capture on monitor {}", monitor_id);
+    let mut burst = config.burst.clone().map(BurstState::new);
+    let mut idle_backoff = config
+        .idle_backoff
+        .clone()
+        .map(|backoff| IdleBackoff::new(backoff, Instant::now()));
 
-    let mut monitor = match get_monitor_by_id(monitor_id).await {
-        Some(m) => m,
-        None => {
-            error!("Monitor {} not found", monitor_id);
-            return Err(anyhow::anyhow!("Monitor not found"));
-        }
+    let hash_algorithm = config.hash_algorithm.unwrap_or_default();
+    let params = DedupPolicyParams {
+        skip_threshold,
+        max_skip: Duration::from_secs(10),
     };
+    let policy = config.dedup_policies.clone().unwrap_or_default().build(
+        config.dedup_policy.as_deref().unwrap_or(DEFAULT_POLICY),
+        &params,
+    )?;
+    let mut dedup = DedupStep::new(policy, hash_algorithm, params.max_skip, Instant::now());
+    let text_gate_threshold = config
+        .ocr_text_gate_threshold
+        .unwrap_or(DEFAULT_OCR_TEXT_GATE_THRESHOLD);
+    let mut text_debounce = config
+        .text_debounce
+        .map(|settle| TextDebounce::new(settle, params.max_skip));
+
+    info!(
+        "Starting capture on monitor {} (dedup threshold {})",
+        monitor_id, skip_threshold
+    );
 
     let mut consecutive_failures = 0;
     const MAX_CONSECUTIVE_FAILURES: u32 = 10;
 
+    let configured_fps = 1.0 / interval.as_secs_f64();
+    let mut rate = RateReport::new(ACHIEVED_FPS_REPORT_INTERVAL, Instant::now());
+    let mut power_paused = false;
+    let mut control = config.control.clone();
+    let power_source = config
+        .power_source
+        .clone()
+        .unwrap_or_else(|| Arc::new(SystemPowerSource::cached()));
+    let idle_source = config
+        .idle_source
+        .clone()
+        .unwrap_or_else(|| Arc::new(SystemIdleSource));
+    let mut idle_gate = config.idle_timeout.map(IdleGate::new);
+    let window_source = config
+        .window_source
+        .clone()
+        .unwrap_or_else(|| Arc::new(SystemWindowSource));
+    let privacy_fail_closed = config.privacy_fail_closed.unwrap_or(false);
+    let mut frames_blocked_privacy: u64 = 0;
+    let mut privacy_blocked = false;
+    let mut warned_window_unknown = false;
+
     loop {
+        // A settled typing burst is stored on every tick, including the ones that skip
+        // capture below (idle, power pause, privacy), so going idle doesn't hold it back.
+        if let Some(settled) = text_debounce
+            .as_mut()
+            .and_then(|debounce| debounce.poll(Instant::now()))
+        {
+            store_settled(&config, monitor_id, settled);
+        }
+
+        if let Some(control) = control.as_mut() {
+            match wait_out_pause(control, &mut rate, monitor_id, configured_fps).await {
+                Ok(Some(paused_for)) => {
+                    info!(
+                        "Monitor {}: capture resumed after {:.1}s paused",
+                        monitor_id,
+                        paused_for.as_secs_f64()
+                    );
+                    rate.restart(Instant::now());
+                }
+                Ok(None) => {}
+                Err(_) => {
+                    info!(
+                        "Monitor {}: control channel closed while paused",
+                        monitor_id
+                    );
+                    flush_text_debounce(&config, monitor_id, &mut text_debounce);
+                    return Ok(());
+                }
+            }
+        }
+
         let capture_start = Instant::now();
-        let _captured_at = Utc::now();
+
+        let base_interval = match &config.power {
+            Some(policy) => match policy.effective_fps(power_source.state(), configured_fps) {
+                None => {
+                    if !power_paused {
+                        info!("Monitor {}: pausing capture on low battery", monitor_id);
+                        power_paused = true;
+                    }
+                    tokio::time::sleep(POWER_PAUSE_POLL_INTERVAL).await;
+                    rate.paused_total += POWER_PAUSE_POLL_INTERVAL;
+                    rate.report_if_due(monitor_id, configured_fps, true, Instant::now());
+                    continue;
+                }
+                Some(effective) => {
+                    if power_paused {
+                        info!("Monitor {}: resuming capture", monitor_id);
+                        power_paused = false;
+                        rate.restart(Instant::now());
+                    }
+                    Duration::try_from_secs_f64(1.0 / effective).unwrap_or(interval)
+                }
+            },
+            None => interval,
+        };
+
+        let mut resumed_from_idle = false;
+        if let Some(gate) = idle_gate.as_mut() {
+            match gate.observe(idle_source.idle_for()) {
+                IdleDecision::Skip => {
+                    tokio::time::sleep(base_interval).await;
+                    continue;
+                }
+                IdleDecision::Capture { resumed } => {
+                    if resumed {
+                        info!(
+                            "Monitor {}: user active again ({} ticks skipped while idle)",
+                            monitor_id,
+                            gate.frames_skipped()
+                        );
+                    }
+                    resumed_from_idle = resumed;
+                }
+            }
+        }
+
+        // Checked before capturing, so nothing is taken while a blocked window has focus.
+        // A focus change after this check can still let one frame through.
+        let mut window = None;
+        if let Some(blocklist) = &config.privacy {
+            window = focused_window_from(Arc::clone(&window_source)).await;
+            let blocked = match &window {
+                Some(window) => blocklist.blocks(window),
+                None => {
+                    if !warned_window_unknown {
+                        warn!(
+                            "Monitor {}: focused window unknown, can't apply the privacy blocklist; {}",
+                            monitor_id,
+                            if privacy_fail_closed {
+                                "skipping frames until it is"
+                            } else {
+                                "recording anyway"
+                            }
+                        );
+                        warned_window_unknown = true;
+                    }
+                    privacy_fail_closed
+                }
+            };
+            if blocked != privacy_blocked {
+                info!(
+                    "Monitor {}: privacy blocklist {} ({} frames blocked so far)",
+                    monitor_id,
+                    if blocked {
+                        "matched, not recording"
+                    } else {
+                        "cleared"
+                    },
+                    frames_blocked_privacy
+                );
+                privacy_blocked = blocked;
+            }
+            if blocked {
+                frames_blocked_privacy += 1;
+                tokio::time::sleep(base_interval).await;
+                continue;
+            }
+        }
 
         // 1. Capture
-        let image = match capture_monitoring_safe(&mut monitor).await {
-            Ok(img) => {
+        let captured = capture_monitoring_safe(&mut monitor).await;
+        // A refresh between attempts may have found the display under a new id.
+        monitor_id = monitor.id();
+        let (image, captured_at) = match captured {
+            Ok(captured) => {
                 consecutive_failures = 0;
-                img
+                captured
             },
             Err(e) => {
                 consecutive_failures += 1;
                 error!("Capture failed ({}): {}", consecutive_failures, e);
                 if consecutive_failures > MAX_CONSECUTIVE_FAILURES {
+                    flush_text_debounce(&config, monitor_id, &mut text_debounce);
                      return Err(anyhow::anyhow!("Too many consecutive capture failures"));
                 }
                 tokio::time::sleep(Duration::from_secs(1)).await;
@@ -61,40 +657,296 @@ pub async fn continuous_capture(
             }
         };
 
-        // 2. Compare
-        let diff = frame_comparer.compare(&image);
-        let skip_threshold = 0.01; // 1% difference
-        
-        let time_since_last = last_capture_time.elapsed();
-        let force_capture = time_since_last >= max_skip_duration;
+        rate.frames += 1;
+        rate.report_if_due(monitor_id, configured_fps, false, Instant::now());
+
+        // 2. Compare, off the async workers: a scroll-aware comparison hashes every
+        // row and column of the full-resolution frame.
+        let compared = Arc::clone(&image);
+        let (comparer, diff) = tokio::task::spawn_blocking(move || {
+            let diff = frame_comparer.compare(&compared);
+            (frame_comparer, diff)
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        frame_comparer = comparer;
+
+        if let Some(dir) = config.debug_dump_dir.clone() {
+            let dump_image = Arc::clone(&image);
+            let dumped = tokio::task::spawn_blocking(move || {
+                let phash = hash_algorithm.hash(&dump_image);
+                dump_debug_frame(
+                    &dir,
+                    monitor_id,
+                    captured_at,
+                    hash_algorithm,
+                    phash,
+                    diff,
+                    &dump_image,
+                )
+            })
+            .await;
+            match dumped {
+                Ok(Ok(path)) => debug!("Dumped frame {} to {}", frame_counter, path.display()),
+                Ok(Err(e)) => warn!("Debug dump failed: {}", e),
+                Err(e) => warn!("Debug dump task panicked: {}", e),
+            }
+        }
+
+        let tick_interval = match burst.as_mut() {
+            Some(state) => state.observe(diff, Instant::now(), base_interval),
+            None => base_interval,
+        };
 
-        if diff < skip_threshold && !force_capture {
-            debug!("Skipping frame {} (diff: {:.4})", frame_counter, diff);
+        let decision = dedup.decide(
+            &image,
+            monitor_id,
+            diff,
+            resumed_from_idle,
+            window.as_ref(),
+            Instant::now(),
+        );
+        match decision {
+            DedupDecision::Kept => rate.kept += 1,
+            DedupDecision::DedupMemory => rate.deduped += 1,
+        }
+        let tick_interval = match idle_backoff.as_mut() {
+            Some(backoff) => {
+                let now = Instant::now();
+                backoff.observe(
+                    diff >= skip_threshold,
+                    now,
+                    tick_interval,
+                    dedup.until_heartbeat(now),
+                )
+            }
+            None => tick_interval,
+        };
+
+        if decision == DedupDecision::DedupMemory {
             frame_counter += 1;
-            tokio::time::sleep(interval).await;
+            tokio::time::sleep(tick_interval).await;
             continue;
         }
 
         // 3. Process (Stub for DB write)
-        last_capture_time = Instant::now();
-        info!("captured frame {} (diff: {:.4}, forced: {})", frame_counter, diff, force_capture);
-
-        // TODO: Write to Postgres here
-        // write_frame_to_db(&image, captured_at).await?;
+        // Resolved only for kept frames; failures leave the window columns NULL.
+        let window = match window {
+            Some(window) => Some(window),
+            None => focused_window_from(Arc::clone(&window_source)).await,
+        };
+        // Downscales the whole frame, so it runs off the async workers too.
+        let gated = Arc::clone(&image);
+        let likely_text =
+            tokio::task::spawn_blocking(move || passes_text_gate(&gated, text_gate_threshold))
+                .await
+                .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        let event = CaptureEvent {
+            image,
+            timestamp: captured_at,
+            frame_number: frame_counter,
+            window,
+            merged_frames: 0,
+            likely_text,
+        };
+        let ready = match text_debounce.as_mut() {
+            Some(debounce) => {
+                let small_change = (skip_threshold..TEXT_DEBOUNCE_MAX_DIFF).contains(&diff);
+                debounce.offer(event, small_change, Instant::now())
+            }
+            None => vec![(event, 0)],
+        };
+        for settled in ready {
+            store_settled(&config, monitor_id, settled);
+        }
 
         frame_counter += 1;
         
         let elapsed = capture_start.elapsed();
-        if elapsed < interval {
-            tokio::time::sleep(interval - elapsed).await;
+        if elapsed < tick_interval {
+            tokio::time::sleep(tick_interval - elapsed).await;
         }
     }
 }
 
-async fn capture_monitoring_safe(monitor: &mut SafeMonitor) -> Result<DynamicImage> {
+/// Store a frame released by [`TextDebounce`], noting how many frames it stands for.
+fn store_settled(
+    config: &CaptureConfig,
+    monitor_id: u32,
+    (mut event, merged): (CaptureEvent, u32),
+) {
+    event.merged_frames = merged;
+    store_frame(config, monitor_id, event);
+}
+
+/// Store a typing burst still held when capture stops, so its end state isn't lost.
+fn flush_text_debounce(
+    config: &CaptureConfig,
+    monitor_id: u32,
+    debounce: &mut Option<TextDebounce<CaptureEvent>>,
+) {
+    if let Some(settled) = debounce.as_mut().and_then(TextDebounce::flush) {
+        store_settled(config, monitor_id, settled);
+    }
+}
+
+/// Hand a kept frame on to storage.
+#[cfg_attr(not(test), allow(unused_variables))]
+fn store_frame(config: &CaptureConfig, monitor_id: u32, event: CaptureEvent) {
+    debug!(
+        "Frame {} on monitor {} ({} merged, likely_text={}): app={:?} title={:?}",
+        event.frame_number,
+        monitor_id,
+        event.merged_frames,
+        event.likely_text,
+        event.window.as_ref().and_then(|w| w.app_name.as_deref()),
+        event
+            .window
+            .as_ref()
+            .and_then(|w| w.window_title.as_deref())
+    );
+
+    // TODO: Write to Postgres here (frame + window context)
+    // write_frame_to_db(&event.image, event.timestamp, &event.window).await?;
+
+    #[cfg(test)]
+    if let Some(sink) = &config.frame_sink {
+        let _ = sink.send((monitor_id, event));
+    }
+}
+
+/// Spawn one capture task per enabled monitor, each at its own resolved rate.
+///
+/// Per-monitor `fps` and `dedup_threshold` come from [`Config::resolve_monitor`];
+/// unconfigured monitors get the global values. Options already set in `base`
+/// (`dedup_threshold` included) win over the config; `base` supplies everything else.
+/// The config is validated first, so a bad rate is an error rather than a panic.
+/// Each task runs under [`supervise`], restarting after a panic when
+/// `restart_on_panic` is set.
+pub fn spawn_monitor_captures<M: FrameSource + Clone + 'static>(
+    config: &Config,
+    monitors: &[M],
+    base: &CaptureConfig,
+) -> Result<Vec<(u32, tokio::task::JoinHandle<Result<()>>)>> {
+    config.validate()?;
+    info!(
+        "Dedup thresholds: frame diff {} (per-monitor overrides apply), phash Hamming distance {}",
+        config.global.dedup_threshold, config.global.hamming_threshold
+    );
+    let restart_policy = config.global.restart_policy();
+    let privacy = match &base.privacy {
+        Some(blocklist) => Some(blocklist.clone()),
+        None => Some(config.privacy.blocklist()?).filter(|blocklist| !blocklist.is_empty()),
+    };
+    // One cached reading of the power state for every monitor.
+    let power_source = base
+        .power_source
+        .clone()
+        .unwrap_or_else(|| Arc::new(SystemPowerSource::cached()));
+    Ok(monitors
+        .iter()
+        .filter_map(|monitor| {
+            let resolved = config.resolve_monitor(monitor.id(), monitor.name());
+            if !resolved.enabled {
+                info!(
+                    "Monitor {} ({}) disabled in config",
+                    monitor.id(),
+                    monitor.name()
+                );
+                return None;
+            }
+            info!(
+                "Monitor {} ({}) capturing at {} fps",
+                monitor.id(),
+                monitor.name(),
+                resolved.fps
+            );
+            let capture_config = CaptureConfig {
+                dedup_threshold: base.dedup_threshold.or(Some(resolved.dedup_threshold)),
+                power: base.power.clone().or_else(|| config.global.power_policy()),
+                power_source: Some(Arc::clone(&power_source)),
+                idle_timeout: base
+                    .idle_timeout
+                    .or(config.global.idle_timeout.map(Duration::from)),
+                privacy: privacy.clone(),
+                privacy_fail_closed: base
+                    .privacy_fail_closed
+                    .or(Some(config.privacy.fail_closed)),
+                idle_backoff: base.idle_backoff.clone().or_else(|| {
+                    (config.global.max_backoff_factor > 1).then_some(IdleBackoffConfig {
+                        max_inactive_secs: resolved.max_inactive_secs,
+                        max_backoff_factor: config.global.max_backoff_factor,
+                    })
+                }),
+                hash_algorithm: base.hash_algorithm.or(Some(config.global.hash_algorithm)),
+                scroll_aware_dedup: base
+                    .scroll_aware_dedup
+                    .or(Some(config.global.scroll_aware_dedup)),
+                dedup_quantize_bits: base
+                    .dedup_quantize_bits
+                    .or(config.global.dedup_quantize_bits),
+                dedup_policy: base
+                    .dedup_policy
+                    .clone()
+                    .or_else(|| Some(config.global.dedup_policy.clone())),
+                text_debounce: base
+                    .text_debounce
+                    .or(config.global.text_debounce.map(Duration::from)),
+                ocr_text_gate_threshold: base
+                    .ocr_text_gate_threshold
+                    .or(Some(config.global.ocr_text_gate_threshold)),
+                ..base.clone()
+            };
+            let task_name = format!("capture-{}", monitor.id());
+            let policy = restart_policy.clone();
+            let interval = resolved.interval();
+            let monitor_for_task = monitor.clone();
+            let handle = tokio::spawn(async move {
+                supervise(&task_name, policy, move || {
+                    capture_loop(monitor_for_task.clone(), interval, capture_config.clone())
+                })
+                .await
+            });
+            Some((monitor.id(), handle))
+        })
+        .collect())
+}
+
+/// Write a raw frame to `dir` as
+/// `monitor{id}_{timestamp}_phash-{algorithm}-{hex}_diff{diff}.png`, naming the hash
+/// algorithm so hashes from different runs aren't compared by mistake.
+/// Debug-only: files are never cleaned up or referenced by storage.
+pub fn dump_debug_frame(
+    dir: &Path,
+    monitor_id: u32,
+    captured_at: DateTime<Utc>,
+    hash_algorithm: HashAlgorithm,
+    phash: u64,
+    diff: f64,
+    image: &DynamicImage,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let file_name = format!(
+        "monitor{}_{}_phash-{}-{:016x}_diff{:.4}.png",
+        monitor_id,
+        captured_at.format("%Y%m%dT%H%M%S%.3fZ"),
+        hash_algorithm.as_str(),
+        phash,
+        diff
+    );
+    let path = dir.join(file_name);
+    image.save(&path)?;
+    Ok(path)
+}
+
+/// Capture with retries. The timestamp is taken when the successful attempt returns,
+/// so retries and refreshes don't skew `captured_at`.
+async fn capture_monitoring_safe(
+    monitor: &mut impl FrameSource,
+) -> Result<(Arc<DynamicImage>, DateTime<Utc>)> {
     for attempt in 0..3 {
         match monitor.capture_image().await {
-            Ok(img) => return Ok(img),
+            Ok(img) => return Ok((Arc::new(img), Utc::now())),
             Err(e) => {
                 debug!("Capture attempt {} failed: {}", attempt, e);
                 // Try refresh
@@ -107,3 +959,589 @@ async fn capture_monitoring_safe(monitor: &mut SafeMonitor) -> Result<DynamicIma
     }
     Err(anyhow::anyhow!("Failed to capture after retries"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedup_policy::{memory_dedup_decision, DEDUP_DECISION_TARGET};
+    use crate::monitor::SafeMonitor;
+    use image::{GrayImage, Luma};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    struct DecisionRecorder(Arc<Mutex<Vec<(String, String)>>>);
+
+    #[derive(Default)]
+    struct DecisionFields {
+        decision: String,
+        reason: String,
+    }
+
+    impl Visit for DecisionFields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            match field.name() {
+                "decision" => self.decision = value.to_string(),
+                "reason" => self.reason = value.to_string(),
+                _ => {}
+            }
+        }
+
+        fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for DecisionRecorder {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+            if event.metadata().target() == DEDUP_DECISION_TARGET {
+                let mut fields = DecisionFields::default();
+                event.record(&mut fields);
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((fields.decision, fields.reason));
+            }
+        }
+    }
+
+    #[test]
+    fn dedup_decisions_are_emitted_per_frame() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(DecisionRecorder(Arc::clone(&events)));
+
+        let dark = Arc::new(DynamicImage::ImageLuma8(GrayImage::from_pixel(
+            64,
+            64,
+            Luma([20]),
+        )));
+        let light = Arc::new(DynamicImage::ImageLuma8(GrayImage::from_pixel(
+            64,
+            64,
+            Luma([230]),
+        )));
+        // (frame, seconds since start, resumed from idle)
+        let frames = [
+            (&dark, 0, false),
+            (&dark, 1, false),
+            (&dark, 12, false),
+            (&light, 13, false),
+            (&light, 14, true),
+        ];
+        let params = DedupPolicyParams {
+            skip_threshold: 0.01,
+            max_skip: Duration::from_secs(10),
+        };
+        let policy = DedupPolicyRegistry::default()
+            .build(DEFAULT_POLICY, &params)
+            .unwrap();
+        let start = Instant::now();
+        let mut step = DedupStep::new(policy, HashAlgorithm::Average, params.max_skip, start);
+
+        let decisions: Vec<DedupDecision> = tracing::subscriber::with_default(subscriber, || {
+            let mut comparer = FrameComparer::new(FrameComparisonConfig::default());
+            frames
+                .iter()
+                .map(|(image, secs, resumed)| {
+                    let diff = comparer.compare(image);
+                    let now = start + Duration::from_secs(*secs);
+                    step.decide(image, 0, diff, *resumed, None, now)
+                })
+                .collect()
+        });
+
+        let recorded = events.lock().unwrap().clone();
+        let expected = [
+            ("kept", "changed"),
+            ("dedup_memory", "below_threshold"),
+            ("kept", "heartbeat"),
+            ("kept", "changed"),
+            ("kept", "idle_resume"),
+        ];
+        assert_eq!(recorded.len(), expected.len());
+        for ((decision, reason), (want_decision, want_reason)) in recorded.iter().zip(expected) {
+            assert_eq!(
+                (decision.as_str(), reason.as_str()),
+                (want_decision, want_reason)
+            );
+        }
+        let logged: Vec<&str> = recorded.iter().map(|(d, _)| d.as_str()).collect();
+        let returned: Vec<&str> = decisions.iter().map(|d| d.as_str()).collect();
+        assert_eq!(logged, returned);
+        assert_eq!(step.last_kept, start + Duration::from_secs(14));
+    }
+
+    #[test]
+    fn dedup_threshold_splits_frames_just_above_and_below() {
+        let base = DynamicImage::ImageLuma8(GrayImage::from_fn(64, 64, |x, _| Luma([x as u8 * 3])));
+        let mut edited = base.to_luma8();
+        for x in 0..8 {
+            for y in 0..8 {
+                edited.put_pixel(x, y, Luma([255]));
+            }
+        }
+        let edited = DynamicImage::ImageLuma8(edited);
+
+        let mut comparer = FrameComparer::new(FrameComparisonConfig::default());
+        comparer.compare(&Arc::new(base));
+        let diff = comparer.compare(&Arc::new(edited));
+        assert!(diff > 0.0 && diff < 1.0, "unexpected diff {diff}");
+
+        let just_below = diff * 0.99;
+        let just_above = (diff * 1.01).min(1.0);
+        assert_eq!(
+            memory_dedup_decision(diff, just_below, false).0,
+            DedupDecision::Kept
+        );
+        assert_eq!(
+            memory_dedup_decision(diff, just_above, false).0,
+            DedupDecision::DedupMemory
+        );
+        assert!(validate_dedup_threshold(just_above).is_ok());
+        assert!(validate_dedup_threshold(1.5).is_err());
+        assert!(validate_dedup_threshold(-0.1).is_err());
+    }
+
+    #[test]
+    fn burst_enters_on_consecutive_active_frames_and_cools_down() {
+        let base = Duration::from_secs(1);
+        let mut state = BurstState::new(BurstConfig {
+            trigger_diff: 0.1,
+            burst_fps: 4.0,
+            cooldown_secs: 3,
+        });
+        let start = Instant::now();
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+
+        // A single active frame is not enough.
+        assert_eq!(state.observe(0.5, at(0.0), base), base);
+        assert!(!state.in_burst(at(0.0)));
+
+        // The second consecutive one enters burst mode.
+        assert_eq!(
+            state.observe(0.5, at(1.0), base),
+            Duration::from_millis(250)
+        );
+        assert!(state.in_burst(at(1.0)));
+
+        // Quiet frames keep the burst until the cooldown runs out.
+        assert_eq!(
+            state.observe(0.0, at(3.5), base),
+            Duration::from_millis(250)
+        );
+        assert_eq!(state.observe(0.0, at(4.0), base), base);
+        assert!(!state.in_burst(at(4.0)));
+    }
+
+    #[test]
+    fn idle_backoff_widens_on_static_screen_and_resets_on_change() {
+        let base = Duration::from_secs(1);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut backoff = IdleBackoff::new(
+            IdleBackoffConfig {
+                max_inactive_secs: 5,
+                max_backoff_factor: 8,
+            },
+            start,
+        );
+
+        let far = Duration::MAX;
+
+        // Duplicates inside the inactivity window keep the base rate.
+        assert_eq!(backoff.observe(false, at(3), base, far), base);
+        // Then the interval doubles per duplicate tick up to the cap.
+        let widened: Vec<_> = [5, 7, 11, 19, 27]
+            .into_iter()
+            .map(|secs| backoff.observe(false, at(secs), base, far).as_secs())
+            .collect();
+        assert_eq!(widened, [2, 4, 8, 8, 8]);
+        // A widened wait stops at the next heartbeat, but never undercuts the base rate.
+        let three = Duration::from_secs(3);
+        assert_eq!(backoff.observe(false, at(35), base, three), three);
+        assert_eq!(backoff.observe(false, at(38), base, Duration::ZERO), base);
+        // A changed frame drops straight back to the base rate.
+        assert_eq!(backoff.observe(true, at(39), base, far), base);
+        assert_eq!(backoff.observe(false, at(40), base, far), base);
+    }
+
+    #[test]
+    fn text_debounce_stores_only_the_end_of_a_typing_burst() {
+        let mut debounce = TextDebounce::new(Duration::from_millis(800), Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Keystrokes every 150 ms: each frame replaces the pending one.
+        for (i, ms) in [0, 150, 300, 450].into_iter().enumerate() {
+            assert!(debounce.offer(i, true, at(ms)).is_empty());
+            assert_eq!(debounce.poll(at(ms + 100)), None);
+        }
+        assert_eq!(debounce.poll(at(1000)), None);
+        // Typing stops: only the last frame is stored, once, standing for the other three.
+        assert_eq!(debounce.poll(at(1250)), Some((3, 3)));
+        assert_eq!(debounce.poll(at(5000)), None);
+
+        // A large change flushes whatever is pending before itself.
+        assert!(debounce.offer(10, true, at(6000)).is_empty());
+        assert_eq!(debounce.offer(11, false, at(6100)), vec![(10, 0), (11, 0)]);
+        assert_eq!(debounce.poll(at(9000)), None);
+    }
+
+    #[test]
+    fn text_debounce_releases_a_burst_that_never_settles() {
+        let mut debounce = TextDebounce::new(Duration::from_millis(800), Duration::from_secs(2));
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Continuous typing: the settle time never passes, but the hold limit does.
+        let mut stored = Vec::new();
+        for (i, ms) in (0..30).map(|i| (i, i * 150)) {
+            stored.extend(debounce.poll(at(ms)));
+            assert!(debounce.offer(i, true, at(ms)).is_empty());
+        }
+        assert_eq!(stored, [(13, 13), (27, 13)]);
+
+        // Whatever is still held comes out when capture stops.
+        assert_eq!(debounce.flush(), Some((29, 1)));
+        assert_eq!(debounce.flush(), None);
+    }
+
+    #[test]
+    fn burst_requires_consecutive_active_frames() {
+        let base = Duration::from_secs(1);
+        let mut state = BurstState::new(BurstConfig::default());
+        let start = Instant::now();
+
+        state.observe(0.5, start, base);
+        state.observe(0.0, start + Duration::from_secs(1), base);
+        let next = state.observe(0.5, start + Duration::from_secs(2), base);
+
+        assert_eq!(next, base);
+        assert!(!state.in_burst(start + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn spawn_rejects_invalid_config_instead_of_panicking() {
+        let mut config = Config::default();
+        config.global.fps = 0.0;
+        let monitors = [SafeMonitor::stub(1, "DELL U2720Q")];
+        let err =
+            spawn_monitor_captures(&config, &monitors, &CaptureConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("fps"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn control_pause_is_counted_across_report_intervals() {
+        let (tx, mut rx) = crate::control::control_channel();
+        let mut rate = RateReport::new(Duration::from_millis(20), Instant::now());
+        assert_eq!(
+            wait_out_pause(&mut rx, &mut rate, 0, 1.0).await.unwrap(),
+            None
+        );
+
+        tx.send(ControlMessage::Pause).unwrap();
+        let resumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            tx.send(ControlMessage::Resume).unwrap();
+        });
+        // The pause spans several report intervals; all of it is counted.
+        let paused_for = wait_out_pause(&mut rx, &mut rate, 0, 1.0)
+            .await
+            .unwrap()
+            .unwrap();
+        resumer.await.unwrap();
+        assert!(paused_for >= Duration::from_millis(80), "{paused_for:?}");
+        assert_eq!(rate.paused_total, paused_for);
+    }
+
+    /// A display that fails its first `failures` captures, then alternates dark and
+    /// light frames. After `frames` captures it pauses and drops the control sender,
+    /// which ends the loop. Clones share everything but the id, like a real display
+    /// handed to a restarted task.
+    #[derive(Clone)]
+    struct FakeMonitor {
+        id: u32,
+        /// Id the display comes back under after a refresh.
+        renumbered_to: Option<u32>,
+        failures: Arc<AtomicU32>,
+        /// Captures that panic, after the failures.
+        panics: Arc<AtomicU32>,
+        frames: usize,
+        /// The `n`th frame the display shows.
+        frame: fn(usize) -> DynamicImage,
+        control: Arc<Mutex<Option<tokio::sync::watch::Sender<ControlMessage>>>>,
+        captured: Arc<Mutex<Vec<DateTime<Utc>>>>,
+    }
+
+    impl FakeMonitor {
+        fn new(
+            id: u32,
+            failures: u32,
+            frames: usize,
+            control: tokio::sync::watch::Sender<ControlMessage>,
+        ) -> Self {
+            Self {
+                id,
+                renumbered_to: None,
+                failures: Arc::new(failures.into()),
+                panics: Arc::default(),
+                frames,
+                frame: alternating_frame,
+                control: Arc::new(Mutex::new(Some(control))),
+                captured: Arc::default(),
+            }
+        }
+    }
+
+    /// Dark and light frames in turn: every frame is a large change.
+    fn alternating_frame(n: usize) -> DynamicImage {
+        let luma = if n.is_multiple_of(2) { 20 } else { 230 };
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(64, 64, Luma([luma])))
+    }
+
+    /// A page gaining one dark 4x4 "character" per frame: every frame is a small change.
+    fn typing_frame(n: usize) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(256, 256, |x, y| {
+            let typed = (y / 4 * 64 + x / 4) as usize;
+            Luma([if typed < n { 20 } else { 200 }])
+        }))
+    }
+
+    impl FrameSource for FakeMonitor {
+        fn id(&self) -> u32 {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "Fake Display"
+        }
+
+        async fn capture_image(&self) -> Result<DynamicImage> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                anyhow::bail!("display asleep");
+            }
+            if self.panics.load(Ordering::SeqCst) > 0 {
+                self.panics.fetch_sub(1, Ordering::SeqCst);
+                panic!("display driver crashed");
+            }
+            let mut captured = self.captured.lock().unwrap();
+            let image = (self.frame)(captured.len());
+            captured.push(Utc::now());
+            if captured.len() == self.frames {
+                if let Some(control) = self.control.lock().unwrap().take() {
+                    control.send(ControlMessage::Pause).unwrap();
+                }
+            }
+            Ok(image)
+        }
+
+        async fn refresh(&mut self) -> Result<()> {
+            self.id = self.renumbered_to.unwrap_or(self.id);
+            Ok(())
+        }
+    }
+
+    /// Drains the frames a finished (or dropped) capture loop handed to storage.
+    fn stored_frames(
+        mut sink: tokio::sync::mpsc::UnboundedReceiver<(u32, CaptureEvent)>,
+    ) -> Vec<(u32, CaptureEvent)> {
+        let mut stored = Vec::new();
+        while let Ok(frame) = sink.try_recv() {
+            stored.push(frame);
+        }
+        stored
+    }
+
+    #[tokio::test]
+    async fn stored_frames_carry_the_time_they_were_captured() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, rx) = crate::control::control_channel();
+        let monitor = FakeMonitor::new(3, 1, 3, tx);
+        let captured = Arc::clone(&monitor.captured);
+        let (sink, stored) = tokio::sync::mpsc::unbounded_channel();
+        let config = CaptureConfig {
+            control: Some(rx),
+            debug_dump_dir: Some(dir.path().to_path_buf()),
+            frame_sink: Some(sink),
+            ..Default::default()
+        };
+
+        let started = Utc::now();
+        capture_loop(monitor, Duration::from_millis(20), config)
+            .await
+            .unwrap();
+        let finished = Utc::now();
+
+        let captured = captured.lock().unwrap().clone();
+        let stored: Vec<_> = stored_frames(stored)
+            .into_iter()
+            .map(|(_, event)| event.timestamp)
+            .collect();
+        assert_eq!(stored.len(), 3);
+        // Stamped when the successful attempt returned, not when the retries started.
+        assert!(stored[0] - started >= chrono::Duration::milliseconds(100));
+        let next_capture = captured[1..].iter().copied().chain([finished]);
+        for ((at, captured), next) in stored.iter().zip(&captured).zip(next_capture) {
+            assert!(
+                captured <= at && *at <= next,
+                "{captured} <= {at} <= {next}"
+            );
+        }
+
+        // The debug dump names each frame with the same timestamp storage saw.
+        let mut dumps: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        dumps.sort();
+        assert_eq!(dumps.len(), 3);
+        for (dump, at) in dumps.iter().zip(&stored) {
+            let prefix = format!("monitor3_{}_", at.format("%Y%m%dT%H%M%S%.3fZ"));
+            assert!(dump.starts_with(&prefix), "{dump} vs {prefix}");
+        }
+    }
+
+    #[tokio::test]
+    async fn capture_continues_under_a_renumbered_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, rx) = crate::control::control_channel();
+        let mut monitor = FakeMonitor::new(3, 1, 2, tx);
+        monitor.renumbered_to = Some(7);
+        let (sink, stored) = tokio::sync::mpsc::unbounded_channel();
+        let config = CaptureConfig {
+            control: Some(rx),
+            debug_dump_dir: Some(dir.path().to_path_buf()),
+            frame_sink: Some(sink),
+            ..Default::default()
+        };
+
+        capture_loop(monitor, Duration::from_millis(20), config)
+            .await
+            .unwrap();
+
+        let monitors: Vec<u32> = stored_frames(stored).iter().map(|(id, _)| *id).collect();
+        assert_eq!(monitors, [7, 7]);
+        for entry in std::fs::read_dir(dir.path()).unwrap() {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            assert!(name.starts_with("monitor7_"), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn spawned_capture_survives_a_panic_when_restart_is_enabled() {
+        for restart_on_panic in [false, true] {
+            let mut config = Config::default();
+            config.global.restart_on_panic = restart_on_panic;
+            let (tx, rx) = crate::control::control_channel();
+            let monitor = FakeMonitor::new(3, 0, 2, tx);
+            monitor.panics.store(1, Ordering::SeqCst);
+            let base = CaptureConfig {
+                control: Some(rx),
+                ..Default::default()
+            };
+
+            let mut handles =
+                spawn_monitor_captures(&config, std::slice::from_ref(&monitor), &base).unwrap();
+            let (id, handle) = handles.pop().unwrap();
+            let result = handle.await.unwrap();
+
+            assert_eq!(id, 3);
+            if restart_on_panic {
+                result.unwrap();
+                assert_eq!(monitor.captured.lock().unwrap().len(), 2);
+            } else {
+                assert!(result.is_err());
+                assert!(monitor.captured.lock().unwrap().is_empty());
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct NoFocusedWindow;
+
+    impl WindowSource for NoFocusedWindow {
+        fn focused(&self) -> Option<WindowContext> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_window_is_skipped_when_privacy_fails_closed() {
+        for fail_closed in [false, true] {
+            let (tx, rx) = crate::control::control_channel();
+            let monitor = FakeMonitor::new(3, 0, 2, tx);
+            let config = CaptureConfig {
+                control: Some(rx),
+                privacy: Some(PrivacyBlocklist::new(&["1password".to_string()], &[]).unwrap()),
+                privacy_fail_closed: Some(fail_closed),
+                window_source: Some(Arc::new(NoFocusedWindow)),
+                ..Default::default()
+            };
+
+            let run = capture_loop(monitor.clone(), Duration::from_millis(20), config);
+            let finished = tokio::time::timeout(Duration::from_millis(300), run).await;
+
+            let captured = monitor.captured.lock().unwrap().len();
+            if fail_closed {
+                assert!(finished.is_err());
+                assert_eq!(captured, 0);
+            } else {
+                finished.unwrap().unwrap();
+                assert_eq!(captured, 2);
+            }
+        }
+    }
+
+    /// Reports the user idle once `after` frames have been captured.
+    #[derive(Debug)]
+    struct IdleAfter {
+        captured: Arc<Mutex<Vec<DateTime<Utc>>>>,
+        after: usize,
+    }
+
+    impl IdleSource for IdleAfter {
+        fn idle_for(&self) -> Option<Duration> {
+            let idle = self.captured.lock().unwrap().len() >= self.after;
+            Some(if idle {
+                Duration::from_secs(3600)
+            } else {
+                Duration::ZERO
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn typing_burst_is_stored_when_the_user_goes_idle() {
+        let (tx, rx) = crate::control::control_channel();
+        let mut monitor = FakeMonitor::new(3, 0, usize::MAX, tx);
+        monitor.frame = typing_frame;
+        let (sink, stored) = tokio::sync::mpsc::unbounded_channel();
+        let config = CaptureConfig {
+            control: Some(rx),
+            frame_sink: Some(sink),
+            dedup_threshold: Some(0.001),
+            text_debounce: Some(Duration::from_millis(50)),
+            idle_timeout: Some(Duration::from_secs(60)),
+            idle_source: Some(Arc::new(IdleAfter {
+                captured: Arc::clone(&monitor.captured),
+                after: 3,
+            })),
+            ..Default::default()
+        };
+
+        // Three frames: the first is new, the next two are typing. Then the user is idle
+        // and no tick captures again.
+        let run = capture_loop(monitor.clone(), Duration::from_millis(20), config);
+        assert!(tokio::time::timeout(Duration::from_millis(400), run)
+            .await
+            .is_err());
+
+        assert_eq!(monitor.captured.lock().unwrap().len(), 3);
+        let merged: Vec<u32> = stored_frames(stored)
+            .iter()
+            .map(|(_, event)| event.merged_frames)
+            .collect();
+        assert_eq!(merged, [0, 1]);
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Whether the machine is on mains power or running from its battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    Ac,
+    Battery {
+        percent: u8,
+    },
+    /// No battery information on this platform or machine; treated like AC.
+    Unknown,
+}
+
+/// Source of the current power state. Abstracted so capture can be tested without a laptop.
+pub trait PowerSource: Send + Sync + fmt::Debug {
+    fn state(&self) -> PowerState;
+}
+
+/// Reads the power state from the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemPowerSource;
+
+/// One entry of `/sys/class/power_supply`, as far as the power state is concerned.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct PowerSupply {
+    /// `type`: "Mains", "Battery", "USB", ...
+    pub kind: String,
+    /// `scope`: "Device" for peripherals (mouse, headset); empty or "System" otherwise.
+    pub scope: String,
+    pub online: bool,
+    /// `status`: "Charging", "Discharging", "Full", "Not charging", ...
+    pub status: String,
+    pub capacity: Option<u8>,
+}
+
+/// Power state from every supply the kernel reports.
+///
+/// Online mains means AC. Peripheral batteries are ignored. Otherwise a system battery
+/// that is discharging means battery, at the mean charge of all system batteries; one
+/// charging or full means AC. "Not charging" alone decides nothing: dual-battery laptops
+/// report it for the idle pack while running from the other.
+pub(crate) fn combine_supplies(supplies: &[PowerSupply]) -> PowerState {
+    if supplies.iter().any(|s| s.kind == "Mains" && s.online) {
+        return PowerState::Ac;
+    }
+    let batteries: Vec<&PowerSupply> = supplies
+        .iter()
+        .filter(|s| s.kind == "Battery" && s.scope != "Device")
+        .collect();
+    let discharging = batteries.iter().any(|b| b.status == "Discharging");
+    if !discharging
+        && batteries
+            .iter()
+            .any(|b| matches!(b.status.as_str(), "Charging" | "Full"))
+    {
+        return PowerState::Ac;
+    }
+    let capacities: Vec<u32> = batteries
+        .iter()
+        .filter_map(|b| b.capacity.map(u32::from))
+        .collect();
+    if capacities.is_empty() {
+        return PowerState::Unknown;
+    }
+    let percent = capacities.iter().sum::<u32>() / capacities.len() as u32;
+    PowerState::Battery {
+        percent: percent.min(100) as u8,
+    }
+}
+
+impl PowerSource for SystemPowerSource {
+    /// Linux reads `/sys/class/power_supply` and combines the entries with
+    /// [`combine_supplies`]. These are blocking reads; wrap in [`CachedPowerSource`].
+    #[cfg(target_os = "linux")]
+    fn state(&self) -> PowerState {
+        use std::path::Path;
+
+        let read = |path: &Path| {
+            std::fs::read_to_string(path)
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+            return PowerState::Unknown;
+        };
+        let supplies: Vec<PowerSupply> = entries
+            .flatten()
+            .map(|entry| {
+                let dir = entry.path();
+                PowerSupply {
+                    kind: read(&dir.join("type")),
+                    scope: read(&dir.join("scope")),
+                    online: read(&dir.join("online")) == "1",
+                    status: read(&dir.join("status")),
+                    capacity: read(&dir.join("capacity")).parse().ok(),
+                }
+            })
+            .collect();
+        combine_supplies(&supplies)
+    }
+
+    /// No power-state detection on this platform: logs a warning once and reports Unknown.
+    #[cfg(not(target_os = "linux"))]
+    fn state(&self) -> PowerState {
+        use std::sync::Once;
+        use tracing::warn;
+
+        static WARN_ONCE: Once = Once::new();
+        WARN_ONCE.call_once(|| warn!("battery detection is not supported on this platform"));
+        PowerState::Unknown
+    }
+}
+
+impl SystemPowerSource {
+    /// The system source, re-read at most every [`POWER_STATE_CACHE_TTL`].
+    pub fn cached() -> CachedPowerSource<Self> {
+        CachedPowerSource::new(SystemPowerSource, POWER_STATE_CACHE_TTL)
+    }
+}
+
+/// How long [`CachedPowerSource`] reuses a reading by default.
+pub const POWER_STATE_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Re-reads the wrapped source at most once per `ttl`. The power state changes slowly,
+/// and one cached source can be shared by every capture task.
+#[derive(Debug)]
+pub struct CachedPowerSource<S> {
+    inner: S,
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, PowerState)>>,
+}
+
+impl<S: PowerSource> CachedPowerSource<S> {
+    pub fn new(inner: S, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl<S: PowerSource> PowerSource for CachedPowerSource<S> {
+    fn state(&self) -> PowerState {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        match *cached {
+            Some((read_at, state)) if read_at.elapsed() < self.ttl => state,
+            _ => {
+                let state = self.inner.state();
+                *cached = Some((Instant::now(), state));
+                state
+            }
+        }
+    }
+}
+
+/// How capture reacts to running on battery.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerPolicy {
+    /// Stop capturing while on battery below this percentage.
+    pub pause_on_battery_below: Option<u8>,
+    /// Capture rate cap while on battery. Only ever slows capture down.
+    pub battery_fps: Option<f64>,
+}
+
+impl PowerPolicy {
+    /// Capture rate to use in `state`, or `None` when capture should pause.
+    pub fn effective_fps(&self, state: PowerState, configured_fps: f64) -> Option<f64> {
+        match state {
+            PowerState::Battery { percent } => {
+                if self.pause_on_battery_below.is_some_and(|min| percent < min) {
+                    return None;
+                }
+                Some(
+                    self.battery_fps
+                        .map_or(configured_fps, |fps| fps.min(configured_fps)),
+                )
+            }
+            PowerState::Ac | PowerState::Unknown => Some(configured_fps),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug)]
+    struct FixedPower(PowerState);
+
+    impl PowerSource for FixedPower {
+        fn state(&self) -> PowerState {
+            self.0
+        }
+    }
+
+    #[test]
+    fn effective_fps_follows_power_state() {
+        let policy = PowerPolicy {
+            pause_on_battery_below: Some(20),
+            battery_fps: Some(0.2),
+        };
+        let fps = |source: &dyn PowerSource| policy.effective_fps(source.state(), 1.0);
+
+        assert_eq!(fps(&FixedPower(PowerState::Ac)), Some(1.0));
+        assert_eq!(fps(&FixedPower(PowerState::Unknown)), Some(1.0));
+        assert_eq!(
+            fps(&FixedPower(PowerState::Battery { percent: 80 })),
+            Some(0.2)
+        );
+        assert_eq!(fps(&FixedPower(PowerState::Battery { percent: 15 })), None);
+        // battery_fps never speeds capture up.
+        assert_eq!(
+            policy.effective_fps(PowerState::Battery { percent: 80 }, 0.1),
+            Some(0.1)
+        );
+    }
+
+    #[test]
+    fn supplies_combine_into_one_power_state() {
+        let supply = |kind: &str, scope: &str, status: &str, capacity: u8| PowerSupply {
+            kind: kind.to_string(),
+            scope: scope.to_string(),
+            online: false,
+            status: status.to_string(),
+            capacity: Some(capacity),
+        };
+        let internal = supply("Battery", "", "Discharging", 40);
+        let mouse = supply("Battery", "Device", "Charging", 90);
+        let idle_pack = supply("Battery", "", "Not charging", 80);
+
+        // A charging wireless mouse does not put the laptop on AC.
+        assert_eq!(
+            combine_supplies(&[internal.clone(), mouse.clone()]),
+            PowerState::Battery { percent: 40 }
+        );
+        // The idle pack of a dual-battery laptop neither decides AC nor hides its charge.
+        assert_eq!(
+            combine_supplies(&[idle_pack.clone(), internal.clone()]),
+            PowerState::Battery { percent: 60 }
+        );
+        let charging = supply("Battery", "System", "Charging", 40);
+        assert_eq!(combine_supplies(&[charging, idle_pack]), PowerState::Ac);
+        let mains = PowerSupply {
+            kind: "Mains".to_string(),
+            online: true,
+            ..Default::default()
+        };
+        assert_eq!(combine_supplies(&[internal, mains]), PowerState::Ac);
+        assert_eq!(combine_supplies(&[mouse]), PowerState::Unknown);
+        assert_eq!(combine_supplies(&[]), PowerState::Unknown);
+    }
+
+    #[test]
+    fn cached_source_rereads_only_after_the_ttl() {
+        #[derive(Debug, Default)]
+        struct Counting(AtomicU32);
+
+        impl PowerSource for Counting {
+            fn state(&self) -> PowerState {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                PowerState::Ac
+            }
+        }
+
+        let reads = |source: &CachedPowerSource<Counting>| source.inner.0.load(Ordering::SeqCst);
+        let cached = CachedPowerSource::new(Counting::default(), Duration::from_secs(60));
+        for _ in 0..5 {
+            assert_eq!(cached.state(), PowerState::Ac);
+        }
+        assert_eq!(reads(&cached), 1);
+
+        let uncached = CachedPowerSource::new(Counting::default(), Duration::ZERO);
+        uncached.state();
+        uncached.state();
+        assert_eq!(reads(&uncached), 2);
+    }
+}
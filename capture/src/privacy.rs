@@ -0,0 +1,103 @@
+use crate::window_context::WindowContext;
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+
+/// One blocklist entry, matched case-insensitively. Entries starting with `^` or
+/// ending with `$` are anchored regexes; anything else is a plain substring.
+#[derive(Debug, Clone)]
+enum Pattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn parse(entry: &str) -> Result<Self> {
+        if entry.starts_with('^') || entry.ends_with('$') {
+            let regex = RegexBuilder::new(entry)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("invalid privacy blocklist regex '{}'", entry))?;
+            Ok(Pattern::Regex(regex))
+        } else {
+            Ok(Pattern::Substring(entry.to_lowercase()))
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Substring(needle) => value.to_lowercase().contains(needle.as_str()),
+            Pattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Apps and window titles that must never be recorded.
+#[derive(Debug, Clone, Default)]
+pub struct PrivacyBlocklist {
+    apps: Vec<Pattern>,
+    titles: Vec<Pattern>,
+}
+
+impl PrivacyBlocklist {
+    pub fn new(apps: &[String], titles: &[String]) -> Result<Self> {
+        let parse = |entries: &[String]| {
+            entries
+                .iter()
+                .map(|e| Pattern::parse(e))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            apps: parse(apps)?,
+            titles: parse(titles)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.apps.is_empty() && self.titles.is_empty()
+    }
+
+    /// Whether a frame taken while `window` is focused must be dropped.
+    pub fn blocks(&self, window: &WindowContext) -> bool {
+        let any = |patterns: &[Pattern], value: &Option<String>| {
+            value
+                .as_deref()
+                .is_some_and(|value| patterns.iter().any(|p| p.matches(value)))
+        };
+        any(&self.apps, &window.app_name) || any(&self.titles, &window.window_title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app: &str, title: &str) -> WindowContext {
+        WindowContext {
+            app_name: Some(app.to_string()),
+            window_title: Some(title.to_string()),
+            process_id: None,
+        }
+    }
+
+    #[test]
+    fn blocklist_matches_substrings_and_anchored_regexes() {
+        let blocklist = PrivacyBlocklist::new(
+            &["1password".to_string(), "^keepass(xc)?$".to_string()],
+            &["^.* - my bank$".to_string()],
+        )
+        .unwrap();
+
+        assert!(blocklist.blocks(&window("1Password.exe", "Vault")));
+        assert!(blocklist.blocks(&window("KeePassXC", "db.kdbx")));
+        assert!(!blocklist.blocks(&window("keepassxc-helper", "db.kdbx")));
+        assert!(blocklist.blocks(&window("firefox", "Statements - My Bank")));
+        assert!(!blocklist.blocks(&window("firefox", "My Bank blog - news")));
+        assert!(!blocklist.blocks(&WindowContext {
+            app_name: None,
+            window_title: None,
+            process_id: None,
+        }));
+
+        assert!(PrivacyBlocklist::new(&["^(unclosed".to_string()], &[]).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Respawn a task that panicked instead of letting it die with the error logged.
+    /// Off by default.
+    pub restart_on_panic: bool,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            restart_on_panic: false,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before restarting a task that panicked after running for `ran_for`, given
+    /// the delay used last time. A run that outlasted `max_backoff` counts as healthy,
+    /// so the backoff starts over instead of staying at its cap for good.
+    fn restart_delay(&self, previous: Option<Duration>, ran_for: Duration) -> Duration {
+        match previous {
+            Some(previous) if ran_for <= self.max_backoff => (previous * 2).min(self.max_backoff),
+            _ => self.initial_backoff,
+        }
+    }
+}
+
+/// Run the task built by `make_task`, respawning it after a panic when the policy allows.
+///
+/// Normal completion (`Ok` or `Err`) is returned as-is; only panics are retried, with
+/// exponential backoff capped at `max_backoff` and reset after a long enough run. A
+/// cancelled task is returned as an error.
+pub async fn supervise<F, Fut>(name: &str, policy: RestartPolicy, mut make_task: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let mut panic_count: u64 = 0;
+    let mut backoff = None;
+
+    loop {
+        let started = Instant::now();
+        match tokio::spawn(make_task()).await {
+            Ok(result) => return result,
+            Err(e) if e.is_panic() && policy.restart_on_panic => {
+                panic_count += 1;
+                let delay = policy.restart_delay(backoff, started.elapsed());
+                error!(
+                    "Task {} panicked (panic #{}), restarting in {:?}: {}",
+                    name, panic_count, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                backoff = Some(delay);
+            }
+            Err(e) => return Err(anyhow::anyhow!("Task {} failed: {}", name, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    type BoxedTask = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+    fn fast_policy(restart_on_panic: bool) -> RestartPolicy {
+        RestartPolicy {
+            restart_on_panic,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    fn panics_once(runs: Arc<AtomicU32>) -> impl FnMut() -> BoxedTask {
+        move || {
+            let runs = Arc::clone(&runs);
+            Box::pin(async move {
+                if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("first run panics");
+                }
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn panicked_task_is_respawned() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let result = supervise("test", fast_policy(true), panics_once(Arc::clone(&runs))).await;
+
+        assert!(result.is_ok());
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn panic_is_fatal_when_restart_disabled() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let result = supervise("test", fast_policy(false), panics_once(Arc::clone(&runs))).await;
+
+        assert!(result.is_err());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn backoff_doubles_and_resets_after_a_healthy_run() {
+        let policy = RestartPolicy {
+            restart_on_panic: true,
+            ..Default::default()
+        };
+        let quick = Duration::from_millis(10);
+
+        let mut delay = None;
+        let mut delays = Vec::new();
+        for _ in 0..8 {
+            delay = Some(policy.restart_delay(delay, quick));
+            delays.push(delay.unwrap().as_secs());
+        }
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+
+        // A day without panics: the next one starts over from the initial backoff.
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(policy.restart_delay(delay, day), policy.initial_backoff);
+    }
+}
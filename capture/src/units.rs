@@ -0,0 +1,278 @@
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A duration written with a unit: "500ms", "90s", "90m", "12h", "30d" or "2w".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(pub Duration);
+
+/// A size written with a unit: "512B", decimal "50GB" or binary "512MiB".
+/// Unit letters are case-insensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+const DURATION_UNITS: [(&str, u64); 6] = [
+    ("w", 7 * 24 * 60 * 60 * 1000),
+    ("d", 24 * 60 * 60 * 1000),
+    ("h", 60 * 60 * 1000),
+    ("m", 60 * 1000),
+    ("s", 1000),
+    ("ms", 1),
+];
+
+const SIZE_UNITS: [(&str, u64); 9] = [
+    ("TiB", 1 << 40),
+    ("GiB", 1 << 30),
+    ("MiB", 1 << 20),
+    ("KiB", 1 << 10),
+    ("TB", 1_000_000_000_000),
+    ("GB", 1_000_000_000),
+    ("MB", 1_000_000),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+/// Split "30d" into (30, "d"). Only whole, non-negative numbers are accepted.
+fn split_number(input: &str) -> Result<(u64, &str), String> {
+    let input = input.trim();
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    if digits == 0 {
+        return Err(format!("'{}' must start with a whole number", input));
+    }
+    let number = input[..digits]
+        .parse()
+        .map_err(|_| format!("'{}' is too large", input))?;
+    Ok((number, input[digits..].trim()))
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = split_number(input)?;
+        let millis_per_unit = match unit {
+            "" => return Err(format!("'{}' needs a unit (ms, s, m, h, d or w)", input)),
+            "M" => {
+                return Err(format!(
+                    "'{}' is ambiguous: use 'm' for minutes or 'd' for days",
+                    input
+                ))
+            }
+            unit => DURATION_UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, millis)| *millis)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown duration unit '{}' in '{}' (use ms, s, m, h, d or w)",
+                        unit, input
+                    )
+                })?,
+        };
+        number
+            .checked_mul(millis_per_unit)
+            .map(|millis| HumanDuration(Duration::from_millis(millis)))
+            .ok_or_else(|| format!("'{}' is too large", input))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    /// The largest unit that represents the duration exactly, e.g. "90m" or "30d".
+    /// Sub-millisecond precision is dropped.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis() as u64;
+        if millis == 0 {
+            return write!(f, "0s");
+        }
+        let (unit, per_unit) = DURATION_UNITS
+            .iter()
+            .find(|(_, per_unit)| millis.is_multiple_of(*per_unit))
+            .expect("every duration is a whole number of milliseconds");
+        write!(f, "{}{}", millis / per_unit, unit)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = split_number(input)?;
+        let bytes_per_unit = match unit.to_ascii_uppercase().as_str() {
+            "" => return Err(format!("'{}' needs a unit, e.g. MB or MiB", input)),
+            prefix @ ("K" | "M" | "G" | "T") => {
+                return Err(format!(
+                    "'{}' is ambiguous: use {p}B (decimal) or {p}iB (binary)",
+                    input,
+                    p = prefix
+                ))
+            }
+            upper => SIZE_UNITS
+                .iter()
+                .find(|(name, _)| name.to_ascii_uppercase() == upper)
+                .map(|(_, bytes)| *bytes)
+                .ok_or_else(|| format!("unknown size unit '{}' in '{}'", unit, input))?,
+        };
+        number
+            .checked_mul(bytes_per_unit)
+            .map(ByteSize)
+            .ok_or_else(|| format!("'{}' is too large", input))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    /// The largest unit that represents the size exactly, decimal or binary.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "0B");
+        }
+        let (unit, per_unit) = SIZE_UNITS
+            .iter()
+            .filter(|(_, per_unit)| self.0.is_multiple_of(*per_unit))
+            .max_by_key(|(_, per_unit)| *per_unit)
+            .expect("every size is a whole number of bytes");
+        write!(f, "{}{}", self.0 / per_unit, unit)
+    }
+}
+
+/// A config value that is either a string with a unit or a bare number in the
+/// field's legacy unit (`retention_days = 30`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Raw {
+    Number(u64),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn legacy_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    unit: Duration,
+) -> Result<HumanDuration, D::Error> {
+    match Raw::deserialize(deserializer)? {
+        Raw::Number(n) => u32::try_from(n)
+            .ok()
+            .and_then(|n| unit.checked_mul(n))
+            .map(HumanDuration)
+            .ok_or_else(|| serde::de::Error::custom(format!("{} is too large", n))),
+        Raw::Text(text) => text.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// `deserialize_with` helpers for fields that used to be bare integers. Numbers keep
+/// their old unit; strings need an explicit one.
+pub mod legacy {
+    use super::*;
+
+    pub fn days<'de, D: Deserializer<'de>>(d: D) -> Result<HumanDuration, D::Error> {
+        legacy_duration(d, Duration::from_secs(24 * 60 * 60))
+    }
+
+    pub fn secs<'de, D: Deserializer<'de>>(d: D) -> Result<HumanDuration, D::Error> {
+        legacy_duration(d, Duration::from_secs(1))
+    }
+
+    pub fn opt_secs<'de, D: Deserializer<'de>>(d: D) -> Result<Option<HumanDuration>, D::Error> {
+        secs(d).map(Some)
+    }
+
+    pub fn opt_millis<'de, D: Deserializer<'de>>(d: D) -> Result<Option<HumanDuration>, D::Error> {
+        legacy_duration(d, Duration::from_millis(1)).map(Some)
+    }
+
+    pub fn mebibytes<'de, D: Deserializer<'de>>(d: D) -> Result<ByteSize, D::Error> {
+        match Raw::deserialize(d)? {
+            Raw::Number(n) => n
+                .checked_mul(1 << 20)
+                .map(ByteSize)
+                .ok_or_else(|| serde::de::Error::custom(format!("{} MiB is too large", n))),
+            Raw::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parses_units_and_rejects_ambiguous_input() {
+        let duration = |s: &str| s.parse::<HumanDuration>().map(Duration::from);
+        assert_eq!(duration("30d"), Ok(Duration::from_secs(30 * 86_400)));
+        assert_eq!(duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(duration(" 90m "), Ok(Duration::from_secs(5400)));
+        assert_eq!(duration("250ms"), Ok(Duration::from_millis(250)));
+        for bad in ["30", "3M", "1.5h", "-1d", "d", "5 fortnights", ""] {
+            assert!(duration(bad).is_err(), "{bad}");
+        }
+
+        let size = |s: &str| s.parse::<ByteSize>().map(|b| b.0);
+        assert_eq!(size("50GB"), Ok(50_000_000_000));
+        assert_eq!(size("512MiB"), Ok(512 << 20));
+        assert_eq!(size("512mib"), Ok(512 << 20));
+        assert_eq!(size("1kb"), Ok(1000));
+        for bad in ["512", "512M", "2g", "1PB", "99999999999TiB"] {
+            assert!(size(bad).is_err(), "{bad}");
+        }
+
+        assert_eq!(HumanDuration(Duration::from_secs(5400)).to_string(), "90m");
+        assert_eq!(ByteSize(512 << 20).to_string(), "512MiB");
+        assert_eq!(ByteSize(50_000_000_000).to_string(), "50GB");
+    }
+
+    #[test]
+    fn legacy_fields_accept_bare_numbers_in_their_old_unit() {
+        #[derive(Deserialize)]
+        struct Legacy {
+            #[serde(deserialize_with = "legacy::days")]
+            retention: HumanDuration,
+            #[serde(deserialize_with = "legacy::mebibytes")]
+            ceiling: ByteSize,
+        }
+        let parse = |s: &str| toml::from_str::<Legacy>(s);
+
+        let old = parse("retention = 30\nceiling = 512").unwrap();
+        let new = parse("retention = \"30d\"\nceiling = \"512MiB\"").unwrap();
+        assert_eq!(old.retention, new.retention);
+        assert_eq!(old.ceiling, new.ceiling);
+        assert!(parse("retention = \"30\"\nceiling = 1").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn duration_display_round_trips(millis in 0u64..=u64::MAX / 1000) {
+            let duration = HumanDuration(Duration::from_millis(millis));
+            prop_assert_eq!(duration.to_string().parse::<HumanDuration>(), Ok(duration));
+        }
+
+        #[test]
+        fn size_display_round_trips(bytes: u64) {
+            let size = ByteSize(bytes);
+            prop_assert_eq!(size.to_string().parse::<ByteSize>(), Ok(size));
+        }
+    }
+}
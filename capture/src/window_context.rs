@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use xcap::Window as XcapWindow;
+
+/// The focused window at capture time. Fields are `None` when the platform doesn't
+/// report them, so frames can be stored with NULLs instead of empty strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowContext {
+    /// Application name as reported by the OS (the executable name on Windows).
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    pub process_id: Option<u32>,
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// Where capture looks up the focused window.
+pub trait WindowSource: Send + Sync + std::fmt::Debug {
+    /// `None` when nothing is focused or the window list can't be read. Blocking.
+    fn focused(&self) -> Option<WindowContext>;
+}
+
+/// Reads the focused window through xcap.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemWindowSource;
+
+impl WindowSource for SystemWindowSource {
+    fn focused(&self) -> Option<WindowContext> {
+        let windows = match XcapWindow::all() {
+            Ok(windows) => windows,
+            Err(e) => {
+                tracing::debug!("Failed to list windows: {}", e);
+                return None;
+            }
+        };
+        let focused = windows
+            .into_iter()
+            .find(|w| w.is_focused().unwrap_or(false) && !w.is_minimized().unwrap_or(false))?;
+        Some(WindowContext {
+            app_name: focused.app_name().ok().and_then(non_empty),
+            window_title: focused.title().ok().and_then(non_empty),
+            process_id: focused.pid().ok(),
+        })
+    }
+}
+
+/// Resolve the currently focused window. Returns `None` when nothing is focused or the
+/// window list can't be read (e.g. missing permissions); capture must carry on either way.
+pub async fn focused_window() -> Option<WindowContext> {
+    focused_window_from(Arc::new(SystemWindowSource)).await
+}
+
+/// [`focused_window`] from any [`WindowSource`], run on the blocking pool.
+pub async fn focused_window_from(source: Arc<dyn WindowSource>) -> Option<WindowContext> {
+    tokio::task::spawn_blocking(move || source.focused())
+        .await
+        .unwrap_or(None)
+}
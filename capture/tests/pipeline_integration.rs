@@ -1,8 +1,10 @@
 use anyhow::Result;
-use recall_capture::pipeline::continuous_capture;
+use chrono::{TimeZone, Utc};
+use image::{DynamicImage, RgbaImage};
+use recall_capture::dedup::HashAlgorithm;
+use recall_capture::pipeline::{continuous_capture, dump_debug_frame, CaptureConfig};
 use std::time::Duration;
 use tokio::time::timeout;
-use tracing_subscriber;
 
 use recall_capture::monitor::list_monitors;
 
@@ -26,7 +28,15 @@ async fn test_live_hardware_capture() -> Result<()> {
 
     // Run capture for 3 seconds
     // We expect it to run continuously, so we wrap in timeout
-    let result = timeout(Duration::from_secs(3), continuous_capture(monitor_id, Duration::from_millis(100))).await;
+    let result = timeout(
+        Duration::from_secs(3),
+        continuous_capture(
+            monitor_id,
+            Duration::from_millis(100),
+            CaptureConfig::default(),
+        ),
+    )
+    .await;
 
     // Timeout is expected (as the loop is infinite)
     // If it returns Ok(Err), that means the internal loop failed
@@ -48,3 +58,29 @@ async fn test_live_hardware_capture() -> Result<()> {
     
     Ok(())
 }
+
+#[test]
+fn test_debug_dump_writes_named_png() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let image =
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 6, image::Rgba([10, 20, 30, 255])));
+    let captured_at = Utc.with_ymd_and_hms(2026, 3, 4, 5, 6, 7).unwrap();
+
+    let path = dump_debug_frame(
+        dir.path(),
+        2,
+        captured_at,
+        HashAlgorithm::Dct,
+        0xdead_beef,
+        0.0125,
+        &image,
+    )?;
+
+    assert_eq!(
+        path.file_name().unwrap().to_str().unwrap(),
+        "monitor2_20260304T050607.000Z_phash-dct-00000000deadbeef_diff0.0125.png"
+    );
+    let reloaded = image::open(&path)?;
+    assert_eq!((reloaded.width(), reloaded.height()), (8, 6));
+    Ok(())
+}